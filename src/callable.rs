@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    process::ExitCode,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    config,
+    env::Env,
+    token::{Interrupt, Stmt, Value},
+};
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    //? Collects arguments beyond `params` into a list.
+    pub rest: Option<String>,
+    pub body: Arc<Vec<Stmt>>,
+    pub closure: Arc<RwLock<Env>>,
+}
+
+impl Function {
+    //? The number of fixed parameters; with a rest parameter this is the minimum.
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn bind(&self, instance: Value) -> Arc<Function> {
+        let environment = Env::with_enclosing(self.closure.clone());
+        environment
+            .write()
+            .unwrap()
+            .define("this".to_string(), instance);
+        Arc::new(Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            rest: self.rest.clone(),
+            body: self.body.clone(),
+            closure: environment,
+        })
+    }
+
+    pub fn call(&self, arguments: Vec<Value>) -> Result<Value, ExitCode> {
+        config::check_interrupt()?;
+        config::enter_call()?;
+        let result = self.invoke(arguments);
+        config::leave_call();
+        result
+    }
+
+    fn invoke(&self, arguments: Vec<Value>) -> Result<Value, ExitCode> {
+        let environment = Env::with_enclosing(self.closure.clone());
+        let mut arguments = arguments.into_iter();
+        for (param, argument) in self.params.iter().zip(arguments.by_ref()) {
+            environment.write().unwrap().define(param.clone(), argument);
+        }
+        if let Some(rest) = &self.rest {
+            config::track_allocation()?;
+            let rest_arguments = Value::List(Arc::new(RwLock::new(arguments.collect())));
+            environment
+                .write()
+                .unwrap()
+                .define(rest.clone(), rest_arguments);
+        }
+
+        for stmt in self.body.iter() {
+            match stmt.evaluate(environment.clone()) {
+                Ok(_) => {}
+                Err(Interrupt::Return(value)) => return Ok(value),
+                Err(Interrupt::Error(exitcode)) => return Err(exitcode),
+                Err(Interrupt::Break | Interrupt::Continue) => {
+                    unreachable!("the parser rejects loop exits outside a loop")
+                }
+            }
+        }
+        Ok(Value::Nil)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    //? Trailing arguments that may be left out, up to `arity + optional` in total;
+    //? `usize::MAX` takes any number of extra arguments.
+    pub optional: usize,
+    pub function: fn(&[Value]) -> Result<Value, ExitCode>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Arc<Class>>,
+    pub methods: HashMap<String, Arc<Function>>,
+}
+
+impl Class {
+    pub fn find_method(&self, name: &str) -> Option<Arc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Arc<Class>,
+    fields: HashMap<String, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Arc<Class>) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self {
+            class,
+            fields: HashMap::new(),
+        }))
+    }
+
+    pub fn get(instance: &Arc<RwLock<Self>>, name: &str) -> Result<Value, ExitCode> {
+        let this = instance.read().unwrap();
+        if let Some(value) = this.fields.get(name) {
+            Ok(value.clone())
+        } else if let Some(method) = this.class.find_method(name) {
+            Ok(Value::Function(
+                method.bind(Value::Instance(instance.clone())),
+            ))
+        } else {
+            eprintln!("Undefined property '{}'.", name);
+            Err(ExitCode::from(70))
+        }
+    }
+
+    pub fn set(&mut self, name: String, value: Value) {
+        self.fields.insert(name, value);
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    cell::RefCell,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+//? Runtime options set from the command line before evaluation starts.
+static SHOW_TYPES: AtomicBool = AtomicBool::new(false);
+static EXIT_FROM_RESULT: AtomicBool = AtomicBool::new(false);
+
+//? Identifiers per an approximation of UAX #31 built from std's Unicode properties; std has
+//? no XID_Start/XID_Continue tables, and Cargo.toml can't add a crate that has them.
+static APPROX_UAX31: AtomicBool = AtomicBool::new(false);
+
+//? Start programs with an empty global environment, without any natives.
+static PURE: AtomicBool = AtomicBool::new(false);
+
+//? Treat warnings (e.g. unreachable code) as errors.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+//? Warn about local variables that are declared but never read.
+static WARN_UNUSED: AtomicBool = AtomicBool::new(false);
+
+//? Fold constant arithmetic on literals before evaluation.
+static OPTIMIZE: AtomicBool = AtomicBool::new(false);
+
+//? `print` ends lines with LF on every platform unless CRLF is asked for.
+static CRLF: AtomicBool = AtomicBool::new(false);
+
+//? Function called automatically after the top-level declarations have run.
+static ENTRY: RwLock<Option<String>> = RwLock::new(None);
+
+//? Arguments after `--` on the command line, handed to the entry function as a list.
+static SCRIPT_ARGS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+//? Cap on heap-backed values in one run. Instances, lists, maps and their new entries count
+//? from when they're built; strings of `TRACKED_STRING_BYTES` or more only while alive.
+static MAX_ALLOCATIONS: AtomicUsize = AtomicUsize::new(usize::MAX);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+//? Cap on the iterations of any single `while` or `for` loop.
+static MAX_ITERATIONS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+//? Cap on nested Lox function calls, reported as a stack overflow.
+static MAX_CALL_DEPTH: AtomicUsize = AtomicUsize::new(1000);
+static CALL_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+//? Program text kept around so runtime errors can show where they happened.
+static SOURCE: RwLock<Option<String>> = RwLock::new(None);
+
+thread_local! {
+    //? Raised from another thread to stop evaluation on this one at the next loop iteration
+    //? or call. Each run gets its own, so concurrent runs don't stop each other.
+    static INTERRUPT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+pub fn set_show_types(enabled: bool) {
+    SHOW_TYPES.store(enabled, Ordering::Relaxed);
+}
+
+pub fn show_types() -> bool {
+    SHOW_TYPES.load(Ordering::Relaxed)
+}
+
+pub fn set_exit_from_result(enabled: bool) {
+    EXIT_FROM_RESULT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn exit_from_result() -> bool {
+    EXIT_FROM_RESULT.load(Ordering::Relaxed)
+}
+
+pub fn set_approx_uax31(enabled: bool) {
+    APPROX_UAX31.store(enabled, Ordering::Relaxed);
+}
+
+pub fn approx_uax31() -> bool {
+    APPROX_UAX31.load(Ordering::Relaxed)
+}
+
+pub fn set_pure(enabled: bool) {
+    PURE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn pure() -> bool {
+    PURE.load(Ordering::Relaxed)
+}
+
+pub fn set_strict(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+pub fn set_warn_unused(enabled: bool) {
+    WARN_UNUSED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn warn_unused() -> bool {
+    WARN_UNUSED.load(Ordering::Relaxed)
+}
+
+pub fn set_optimize(enabled: bool) {
+    OPTIMIZE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn optimize() -> bool {
+    OPTIMIZE.load(Ordering::Relaxed)
+}
+
+pub fn set_crlf(enabled: bool) {
+    CRLF.store(enabled, Ordering::Relaxed);
+}
+
+pub fn line_ending() -> &'static str {
+    if CRLF.load(Ordering::Relaxed) {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+pub fn set_entry(entry: Option<String>) {
+    *ENTRY.write().unwrap() = entry;
+}
+
+pub fn entry() -> Option<String> {
+    ENTRY.read().unwrap().clone()
+}
+
+pub fn set_script_args(args: Vec<String>) {
+    *SCRIPT_ARGS.write().unwrap() = args;
+}
+
+pub fn script_args() -> Vec<String> {
+    SCRIPT_ARGS.read().unwrap().clone()
+}
+
+pub fn set_source(source: Option<String>) {
+    *SOURCE.write().unwrap() = source;
+}
+
+pub fn source() -> Option<String> {
+    SOURCE.read().unwrap().clone()
+}
+
+pub fn set_max_allocations(limit: Option<usize>) {
+    MAX_ALLOCATIONS.store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+}
+
+pub fn track_allocation() -> Result<(), ExitCode> {
+    let count = ALLOCATIONS.fetch_add(1, Ordering::Relaxed) + 1;
+    if count > MAX_ALLOCATIONS.load(Ordering::Relaxed) {
+        eprintln!("Memory limit exceeded.");
+        Err(ExitCode::from(70))
+    } else {
+        Ok(())
+    }
+}
+
+//? Shorter strings are too cheap to count against the cap.
+pub const TRACKED_STRING_BYTES: usize = 64;
+
+//? One unit of the allocation count, given back when dropped. A clone holds a unit of its
+//? own, so copies of a value count while they're alive too.
+#[derive(Debug)]
+pub struct Allocation(());
+
+impl Allocation {
+    pub fn new() -> Result<Self, ExitCode> {
+        track_allocation()?;
+        Ok(Allocation(()))
+    }
+}
+
+impl Clone for Allocation {
+    fn clone(&self) -> Self {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        Allocation(())
+    }
+}
+
+impl Drop for Allocation {
+    //? Saturates, as `set_max_allocations` may have reset the count while this was alive.
+    fn drop(&mut self) {
+        let _ = ALLOCATIONS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            Some(count.saturating_sub(1))
+        });
+    }
+}
+
+pub fn set_max_iterations(limit: Option<usize>) {
+    MAX_ITERATIONS.store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+//? `iteration` counts from 1 for each loop separately.
+pub fn check_iterations(iteration: usize) -> Result<(), ExitCode> {
+    let limit = MAX_ITERATIONS.load(Ordering::Relaxed);
+    if iteration > limit {
+        eprintln!("Loop exceeded {} iterations.", limit);
+        Err(ExitCode::from(70))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn set_max_call_depth(limit: Option<usize>) {
+    MAX_CALL_DEPTH.store(limit.unwrap_or(1000), Ordering::Relaxed);
+    CALL_DEPTH.store(0, Ordering::Relaxed);
+}
+
+pub fn max_call_depth() -> usize {
+    MAX_CALL_DEPTH.load(Ordering::Relaxed)
+}
+
+//? Every successful `enter_call` must be paired with a `leave_call`.
+pub fn enter_call() -> Result<(), ExitCode> {
+    let depth = CALL_DEPTH.fetch_add(1, Ordering::Relaxed) + 1;
+    if depth > max_call_depth() {
+        CALL_DEPTH.fetch_sub(1, Ordering::Relaxed);
+        eprintln!("Stack overflow.");
+        Err(ExitCode::from(70))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn leave_call() {
+    CALL_DEPTH.fetch_sub(1, Ordering::Relaxed);
+}
+
+//? Runs `f` stopping at the next check once `flag` is raised, then restores the previous flag.
+pub fn with_interrupt<T>(flag: Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    let previous = INTERRUPT.with(|interrupt| interrupt.replace(Some(flag)));
+    let result = f();
+    INTERRUPT.with(|interrupt| interrupt.replace(previous));
+    result
+}
+
+pub fn check_interrupt() -> Result<(), ExitCode> {
+    let interrupted = INTERRUPT.with(|interrupt| {
+        interrupt
+            .borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    });
+    if interrupted {
+        Err(ExitCode::from(70))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt::Display;
+
+use crate::token::{Token, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    //? What the diagnostic points at, e.g. `'x'` or `end`; scanner errors have none.
+    pub location: Option<String>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "[line {}] {} at {}: {}",
+                self.line, severity, location, self.message
+            ),
+            None => write!(f, "[line {}] {}: {}", self.line, severity, self.message),
+        }
+    }
+}
+
+impl Diagnostic {
+    //? The headline followed by the source snippet, when the line exists.
+    pub fn render(&self, source: &str) -> String {
+        match snippet(source, self.line, self.column) {
+            Some(snippet) => format!("{}\n{}", self, snippet),
+            None => self.to_string(),
+        }
+    }
+}
+
+//? Everything a front-end phase found wrong, in the order it was found.
+#[derive(Default)]
+pub struct Diagnostics {
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn error(&mut self, token: &Token, message: &str) {
+        self.push(Severity::Error, token, message);
+    }
+
+    pub fn warning(&mut self, token: &Token, message: &str) {
+        self.push(Severity::Warning, token, message);
+    }
+
+    fn push(&mut self, severity: Severity, token: &Token, message: &str) {
+        let location = match token.token_type {
+            TokenType::Eof => "end".to_string(),
+            _ => format!("'{}'", token.lexeme),
+        };
+        self.list.push(Diagnostic {
+            severity,
+            line: token.line,
+            column: token.column,
+            location: Some(location),
+            message: message.to_string(),
+        });
+    }
+
+    pub fn error_at(&mut self, line: usize, column: usize, message: &str) {
+        self.list.push(Diagnostic {
+            severity: Severity::Error,
+            line,
+            column,
+            location: None,
+            message: message.to_string(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn as_slice(&self) -> &[Diagnostic] {
+        &self.list
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+//? Tabs in a snippet expand to the next multiple of this many columns.
+const TAB_WIDTH: usize = 4;
+
+//? Renders the source line and a caret under `column`. Tabs expand to spaces and wide
+//? characters take two columns, so the caret lines up the way a terminal draws the line.
+pub fn snippet(source: &str, line: usize, column: usize) -> Option<String> {
+    let text = source.lines().nth(line.checked_sub(1)?)?;
+    let mut expanded = String::new();
+    let mut caret = None;
+    for (i, c) in text.chars().enumerate() {
+        let start = display_width(&expanded);
+        if i + 1 == column {
+            caret = Some(start);
+        }
+        if c == '\t' {
+            expanded.push_str(&" ".repeat(TAB_WIDTH - start % TAB_WIDTH));
+        } else {
+            expanded.push(c);
+        }
+    }
+    let caret = caret.unwrap_or_else(|| display_width(&expanded));
+    Some(format!("    {}\n    {}^", expanded, " ".repeat(caret)))
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+//? East Asian Wide and Fullwidth ranges, which terminals draw two columns wide.
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x2FFFD
+            | 0x30000..=0x3FFFD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::natives;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    //? Every diagnostic the front end reports for `source`, in phase order.
+    fn front_end(source: &str) -> Vec<Diagnostic> {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        let mut diagnostics = scanner.diagnostics().to_vec();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        diagnostics.extend_from_slice(parser.diagnostics());
+        if !has_errors(&diagnostics) {
+            let mut resolver = Resolver::with_known_globals(natives::names());
+            resolver.resolve(parser.statements());
+            diagnostics.extend_from_slice(resolver.diagnostics());
+        }
+        diagnostics
+    }
+
+    fn headlines(source: &str) -> Vec<String> {
+        front_end(source)
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn every_phase_reports_in_the_same_format() {
+        assert_eq!(
+            headlines("var a = 1 @ 2;"),
+            ["[line 1] Error: Unexpected character: @"]
+        );
+        assert_eq!(
+            headlines("var x = ;"),
+            ["[line 1] Error at ';': Expect expression."]
+        );
+        assert_eq!(
+            headlines("print (1"),
+            ["[line 1] Error at end: Unmatched parentheses."]
+        );
+        assert_eq!(
+            headlines("{\n  var a = a;\n}"),
+            ["[line 2] Error at 'a': Can't read local variable in its own initializer."]
+        );
+    }
+
+    #[test]
+    fn warnings_are_not_errors() {
+        let diagnostics = front_end("fun f() { return 1; print 2; }");
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "[line 1] Warning at 'print': Unreachable code."
+        );
+        assert!(!has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn scanner_errors_keep_scanning() {
+        assert_eq!(
+            headlines("@\nprint 1;\n#"),
+            [
+                "[line 1] Error: Unexpected character: @",
+                "[line 3] Error: Unexpected character: #"
+            ]
+        );
+    }
+
+    #[test]
+    fn lexical_and_syntax_errors_are_both_reported_in_order() {
+        assert_eq!(
+            headlines("var a = @;\nprint (1;"),
+            [
+                "[line 1] Error: Unexpected character: @",
+                "[line 2] Error at ';': Unmatched parentheses."
+            ]
+        );
+    }
+
+    fn scan_error(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        scanner.diagnostics()[0].render(source)
+    }
+
+    #[test]
+    fn caret_expands_leading_tabs() {
+        let source = "fun f() {\n\t\tvar a = 1 @ 2;\n}\n";
+        assert_eq!(
+            scan_error(source),
+            format!(
+                "[line 2] Error: Unexpected character: @\n    {}var a = 1 @ 2;\n    {}^",
+                " ".repeat(8),
+                " ".repeat(18)
+            )
+        );
+    }
+
+    #[test]
+    fn caret_expands_tabs_to_the_next_stop() {
+        //? The first tab fills 4 columns, the second only the 1 left after `  print`.
+        let source = "\t  print\t\"x\" # 1;";
+        assert_eq!(
+            scan_error(source),
+            format!(
+                "[line 1] Error: Unexpected character: #\n    {}print \"x\" # 1;\n    {}^",
+                " ".repeat(6),
+                " ".repeat(16)
+            )
+        );
+    }
+
+    #[test]
+    fn caret_counts_multibyte_characters_once() {
+        let source = "print \"héllo wörld\" $;";
+        assert_eq!(
+            scan_error(source),
+            format!(
+                "[line 1] Error: Unexpected character: $\n    print \"héllo wörld\" $;\n    {}^",
+                " ".repeat(20)
+            )
+        );
+    }
+
+    #[test]
+    fn caret_counts_wide_characters_twice() {
+        let source = "var 日本 = \"語\"; ~";
+        assert_eq!(
+            scan_error(source),
+            format!(
+                "[line 1] Error: Unexpected character: ~\n    var 日本 = \"語\"; ~\n    {}^",
+                " ".repeat(17)
+            )
+        );
+    }
+
+    #[test]
+    fn snippet_points_at_parser_error_columns() {
+        let mut diagnostics = Diagnostics::default();
+        let mut scanner = Scanner::new("\tvar\tx = ;");
+        scanner.tokenize();
+        let token = &scanner.tokens()[3];
+        diagnostics.error(token, "Expect expression.");
+        let diagnostic = &diagnostics.as_slice()[0];
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 10));
+        assert_eq!(
+            snippet("\tvar\tx = ;", diagnostic.line, diagnostic.column).unwrap(),
+            format!("    {}var x = ;\n    {}^", " ".repeat(4), " ".repeat(12))
+        );
+    }
+
+    #[test]
+    fn snippet_is_none_past_the_last_line() {
+        assert_eq!(snippet("print 1;", 2, 1), None);
+        assert_eq!(snippet("print 1;", 0, 1), None);
+    }
+}
@@ -1,14 +1,17 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     process::ExitCode,
     sync::{Arc, RwLock},
 };
 
-use crate::token::Value;
+use crate::{callable::Native, token::Value};
 
 #[derive(Debug, Clone)]
 pub struct Env {
     values: HashMap<String, Value>,
+    //? Names in the order they were first defined, so listings are reproducible.
+    order: Vec<String>,
+    constants: HashSet<String>,
     enclosing: Option<Arc<RwLock<Env>>>,
 }
 
@@ -16,6 +19,8 @@ impl Env {
     pub fn new() -> Arc<RwLock<Self>> {
         Arc::new(RwLock::new(Env {
             values: HashMap::new(),
+            order: vec![],
+            constants: HashSet::new(),
             enclosing: None,
         }))
     }
@@ -23,26 +28,143 @@ impl Env {
     pub fn with_enclosing(enclosing: Arc<RwLock<Env>>) -> Arc<RwLock<Self>> {
         Arc::new(RwLock::new(Self {
             values: HashMap::new(),
+            order: vec![],
+            constants: HashSet::new(),
             enclosing: Some(enclosing),
         }))
     }
 
-    pub fn define(&mut self, name: String, value: Value) {
+    pub fn names(&self) -> Vec<String> {
+        self.order.clone()
+    }
+
+    //? This scope's own variables in definition order, without the enclosing ones.
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        self.order
+            .iter()
+            .map(|name| (name.clone(), self.values[name].clone()))
+            .collect()
+    }
+
+    fn insert(&mut self, name: String, value: Value) {
+        if !self.values.contains_key(&name) {
+            self.order.push(name.clone());
+        }
         self.values.insert(name, value);
     }
 
+    //? How many scopes enclose this one: 0 for the globals. A debugging aid, so nothing in
+    //? the interpreter itself calls it.
+    #[allow(dead_code)]
+    pub fn depth(&self) -> usize {
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.read().unwrap().depth() + 1,
+            None => 0,
+        }
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.constants.remove(&name);
+        self.insert(name, value);
+    }
+
+    //? Natives always live in the global scope, whichever scope registers them.
+    pub fn define_native(&mut self, native: Native) {
+        self.define_global(native.name.to_string(), Value::Native(native));
+    }
+
+    pub fn define_global(&mut self, name: String, value: Value) {
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.write().unwrap().define_global(name, value),
+            None => self.define(name, value),
+        }
+    }
+
+    //? The outermost environment of `environment`'s chain.
+    pub fn root(environment: &Arc<RwLock<Env>>) -> Arc<RwLock<Env>> {
+        match environment.read().unwrap().enclosing {
+            Some(ref enclosing) => Self::root(enclosing),
+            None => environment.clone(),
+        }
+    }
+
+    pub fn define_constant(&mut self, name: String, value: Value) {
+        self.constants.insert(name.clone());
+        self.insert(name, value);
+    }
+
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), ExitCode> {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value);
-            Ok(())
-        } else if value == Value::Nil {
-            self.values.insert(name.to_string(), value);
+        if self.constants.contains(name) {
+            eprintln!("Cannot assign to constant '{}'.", name);
+            Err(ExitCode::from(70))
+        } else if self.values.contains_key(name) || value == Value::Nil {
+            self.insert(name.to_string(), value);
             Ok(())
         } else if let Some(ref mut enclosing) = self.enclosing {
             enclosing.write().unwrap().assign(name, value)
         } else {
             eprintln!("Undefined assign variable '{}'.", name);
-            return Err(ExitCode::from(70));
+            Err(ExitCode::from(70))
+        }
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &str, value: Value) -> Result<(), ExitCode> {
+        if distance == 0 {
+            self.assign(name, value)
+        } else if let Some(ref mut enclosing) = self.enclosing {
+            enclosing
+                .write()
+                .unwrap()
+                .assign_at(distance - 1, name, value)
+        } else {
+            eprintln!("Undefined assign variable '{}'.", name);
+            Err(ExitCode::from(70))
+        }
+    }
+
+    pub fn assign_global(&mut self, name: &str, value: Value) -> Result<(), ExitCode> {
+        if let Some(ref mut enclosing) = self.enclosing {
+            enclosing.write().unwrap().assign_global(name, value)
+        } else {
+            self.assign(name, value)
+        }
+    }
+
+    pub fn get_at(&self, distance: usize, name: &str) -> Result<Value, ExitCode> {
+        if distance == 0 {
+            if let Some(value) = self.values.get(name) {
+                return Ok(value.clone());
+            }
+        } else if let Some(ref enclosing) = self.enclosing {
+            return enclosing.read().unwrap().get_at(distance - 1, name);
+        }
+        eprintln!("Undefined get variable '{}'.", name);
+        Err(ExitCode::from(70))
+    }
+
+    //? Like `get_at`/`get_global`, but reports nothing when the name is missing.
+    pub fn peek_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            self.values.get(name).cloned()
+        } else {
+            self.enclosing
+                .as_ref()
+                .and_then(|enclosing| enclosing.read().unwrap().peek_at(distance - 1, name))
+        }
+    }
+
+    pub fn peek_global(&self, name: &str) -> Option<Value> {
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.read().unwrap().peek_global(name),
+            None => self.values.get(name).cloned(),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Result<Value, ExitCode> {
+        if let Some(ref enclosing) = self.enclosing {
+            enclosing.read().unwrap().get_global(name)
+        } else {
+            self.get(name)
         }
     }
 
@@ -53,7 +175,117 @@ impl Env {
             enclosing.read().unwrap().get(name)
         } else {
             eprintln!("Undefined get variable '{}'.", name);
-            return Err(ExitCode::from(70));
+            Err(ExitCode::from(70))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_lists_own_definitions_in_order() {
+        let globals = Env::new();
+        globals
+            .write()
+            .unwrap()
+            .define("b".to_string(), Value::Number(1.0));
+        globals.write().unwrap().define("a".to_string(), Value::Nil);
+        globals
+            .write()
+            .unwrap()
+            .define("b".to_string(), Value::Number(2.0));
+        assert_eq!(globals.read().unwrap().names(), ["b", "a"]);
+    }
+
+    #[test]
+    fn names_excludes_enclosing_scopes() {
+        let globals = Env::new();
+        globals
+            .write()
+            .unwrap()
+            .define("outer".to_string(), Value::Nil);
+        let local = Env::with_enclosing(globals.clone());
+        local
+            .write()
+            .unwrap()
+            .define("inner".to_string(), Value::Boolean(true));
+        assert_eq!(local.read().unwrap().names(), ["inner"]);
+        assert_eq!(globals.read().unwrap().names(), ["outer"]);
+        assert_eq!(local.read().unwrap().get("outer").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn depth_counts_enclosing_scopes() {
+        let globals = Env::new();
+        let function = Env::with_enclosing(globals.clone());
+        let block = Env::with_enclosing(function.clone());
+        assert_eq!(globals.read().unwrap().depth(), 0);
+        assert_eq!(function.read().unwrap().depth(), 1);
+        assert_eq!(block.read().unwrap().depth(), 2);
+    }
+
+    #[test]
+    fn inner_scope_reads_and_assigns_outer_variables() {
+        let globals = Env::new();
+        globals
+            .write()
+            .unwrap()
+            .define("x".to_string(), Value::Number(1.0));
+        let local = Env::with_enclosing(globals.clone());
+        assert_eq!(local.read().unwrap().get("x").unwrap(), Value::Number(1.0));
+        assert!(local
+            .write()
+            .unwrap()
+            .assign("x", Value::Number(2.0))
+            .is_ok());
+        assert_eq!(
+            globals.read().unwrap().get("x").unwrap(),
+            Value::Number(2.0)
+        );
+    }
+
+    #[test]
+    fn defining_in_inner_scope_shadows_outer() {
+        let globals = Env::new();
+        globals
+            .write()
+            .unwrap()
+            .define("x".to_string(), Value::Number(1.0));
+        let local = Env::with_enclosing(globals.clone());
+        local
+            .write()
+            .unwrap()
+            .define("x".to_string(), Value::Number(2.0));
+        assert!(local
+            .write()
+            .unwrap()
+            .assign("x", Value::Number(3.0))
+            .is_ok());
+        assert_eq!(local.read().unwrap().get("x").unwrap(), Value::Number(3.0));
+        assert_eq!(
+            globals.read().unwrap().get("x").unwrap(),
+            Value::Number(1.0)
+        );
+    }
+
+    #[test]
+    fn define_global_is_visible_from_another_nested_scope() {
+        let globals = Env::new();
+        let first = Env::with_enclosing(Env::with_enclosing(globals.clone()));
+        first
+            .write()
+            .unwrap()
+            .define_global("shared".to_string(), Value::Number(1.0));
+        assert!(first.read().unwrap().names().is_empty());
+
+        let second = Env::with_enclosing(globals.clone());
+        assert_eq!(
+            second.read().unwrap().get("shared").unwrap(),
+            Value::Number(1.0)
+        );
+        assert!(Arc::ptr_eq(&Env::root(&first), &globals));
+        assert!(Arc::ptr_eq(&Env::root(&second), &globals));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::token::{Expr, Stmt, Token, TokenType, Value};
+
+//? Quotes and escapes `s` as a JSON string literal.
+pub fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+//? One object per line; `literal` is only present for strings and numbers.
+pub fn tokens(tokens: &[Token]) -> String {
+    let objects: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            let literal = match &token.token_type {
+                TokenType::String(s) => format!(", \"literal\": {}", string(s)),
+                TokenType::Number(n) => format!(", \"literal\": {:?}", n),
+                _ => String::new(),
+            };
+            format!(
+                "  {{\"type\": {}, \"lexeme\": {}, \"line\": {}{}}}",
+                string(&token.token_type.to_string()),
+                string(&token.lexeme),
+                token.line,
+                literal
+            )
+        })
+        .collect();
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+//? One variable per line, skipping natives. Lists and maps nest as arrays and objects;
+//? functions, classes and instances appear as their printed form.
+pub fn globals(entries: &[(String, Value)]) -> String {
+    let fields: Vec<String> = entries
+        .iter()
+        .filter(|(_, value)| !matches!(value, Value::Native(_)))
+        .map(|(name, value)| format!("  {}: {}", string(name), self::value(value)))
+        .collect();
+    if fields.is_empty() {
+        return "{}".to_string();
+    }
+    format!("{{\n{}\n}}", fields.join(",\n"))
+}
+
+//? Numbers JSON can't represent (`NaN`, infinities) become `null`.
+fn value(value: &Value) -> String {
+    match value {
+        Value::Number(n) if n.is_finite() => format!("{:?}", n),
+        Value::Number(_) | Value::Nil => "null".to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::String(s) => string(s),
+        Value::List(list) => {
+            let elements: Vec<String> = list.read().unwrap().iter().map(self::value).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Value::Map(map) => {
+            let entries: Vec<String> = map
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(key, value)| format!("{}: {}", string(&key.to_string()), self::value(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        value => string(&value.to_string()),
+    }
+}
+
+//? One top-level statement per line; every node is an object tagged with its `kind`.
+pub fn statements(stmts: &[Stmt]) -> String {
+    let nodes: Vec<String> = stmts
+        .iter()
+        .map(|stmt| format!("  {}", statement(stmt)))
+        .collect();
+    format!("[\n{}\n]", nodes.join(",\n"))
+}
+
+fn list(stmts: &[Stmt]) -> String {
+    let nodes: Vec<String> = stmts.iter().map(statement).collect();
+    format!("[{}]", nodes.join(", "))
+}
+
+fn optional(stmt: &Option<Box<Stmt>>) -> String {
+    match stmt {
+        Some(stmt) => statement(stmt),
+        None => "null".to_string(),
+    }
+}
+
+fn statement(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Block(stmts) => format!("{{\"kind\": \"Block\", \"body\": {}}}", list(stmts)),
+        Stmt::Print(values) => {
+            let values: Vec<String> = values.iter().map(expression).collect();
+            format!(
+                "{{\"kind\": \"Print\", \"values\": [{}]}}",
+                values.join(", ")
+            )
+        }
+        Stmt::While(condition, body) => format!(
+            "{{\"kind\": \"While\", \"condition\": {}, \"body\": {}}}",
+            statement(condition),
+            statement(body)
+        ),
+        Stmt::DoWhile(body, condition) => format!(
+            "{{\"kind\": \"DoWhile\", \"body\": {}, \"condition\": {}}}",
+            statement(body),
+            statement(condition)
+        ),
+        Stmt::Repeat(count, body) => format!(
+            "{{\"kind\": \"Repeat\", \"count\": {}, \"body\": {}}}",
+            statement(count),
+            statement(body)
+        ),
+        Stmt::For(init, condition, increment, body) => format!(
+            "{{\"kind\": \"For\", \"init\": {}, \"condition\": {}, \"increment\": {}, \"body\": {}}}",
+            optional(init),
+            optional(condition),
+            optional(increment),
+            statement(body)
+        ),
+        Stmt::If(condition, if_branch, else_branch) => format!(
+            "{{\"kind\": \"If\", \"condition\": {}, \"then\": {}, \"else\": {}}}",
+            statement(condition),
+            statement(if_branch),
+            optional(else_branch)
+        ),
+        Stmt::Switch(value, cases, default) => {
+            let cases: Vec<String> = cases
+                .iter()
+                .map(|(case, body)| {
+                    format!(
+                        "{{\"value\": {}, \"body\": {}}}",
+                        expression(case),
+                        list(body)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"kind\": \"Switch\", \"value\": {}, \"cases\": [{}], \"default\": {}}}",
+                expression(value),
+                cases.join(", "),
+                default.as_deref().map_or("null".to_string(), list)
+            )
+        }
+        Stmt::Declare(name, value, constant) => format!(
+            "{{\"kind\": \"Declare\", \"name\": {}, \"constant\": {}, \"value\": {}}}",
+            string(&name.lexeme),
+            constant,
+            optional(value)
+        ),
+        Stmt::Assign(name, value, _) => format!(
+            "{{\"kind\": \"Assign\", \"name\": {}, \"value\": {}}}",
+            string(name),
+            statement(value)
+        ),
+        Stmt::Function(name, params, rest, body) => {
+            let params: Vec<String> = params.iter().map(|param| string(param)).collect();
+            format!(
+                "{{\"kind\": \"Function\", \"name\": {}, \"params\": [{}], \"rest\": {}, \"body\": {}}}",
+                string(name),
+                params.join(", "),
+                rest.as_deref().map_or("null".to_string(), string),
+                list(body)
+            )
+        }
+        Stmt::Class(name, superclass, methods) => format!(
+            "{{\"kind\": \"Class\", \"name\": {}, \"superclass\": {}, \"methods\": {}}}",
+            string(name),
+            superclass.as_ref().map_or("null".to_string(), expression),
+            list(methods)
+        ),
+        Stmt::Return(value) => format!(
+            "{{\"kind\": \"Return\", \"value\": {}}}",
+            value.as_ref().map_or("null".to_string(), expression)
+        ),
+        Stmt::Break => "{\"kind\": \"Break\"}".to_string(),
+        Stmt::Continue => "{\"kind\": \"Continue\"}".to_string(),
+        Stmt::Expr(expr) => expression(expr),
+    }
+}
+
+fn expression(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(left, operator, right) => format!(
+            "{{\"kind\": \"Binary\", \"operator\": {}, \"left\": {}, \"right\": {}}}",
+            string(&operator.lexeme),
+            expression(left),
+            expression(right)
+        ),
+        Expr::Literal(token) => {
+            let value = match &token.token_type {
+                TokenType::String(s) => string(s),
+                TokenType::Number(n) => format!("{:?}", n),
+                TokenType::True => "true".to_string(),
+                TokenType::False => "false".to_string(),
+                TokenType::Nil => "null".to_string(),
+                _ => string(&token.lexeme),
+            };
+            format!("{{\"kind\": \"Literal\", \"value\": {}}}", value)
+        }
+        Expr::Variable(name, _) => {
+            format!(
+                "{{\"kind\": \"Variable\", \"name\": {}}}",
+                string(&name.lexeme)
+            )
+        }
+        Expr::Unary(operator, right) => format!(
+            "{{\"kind\": \"Unary\", \"operator\": {}, \"right\": {}}}",
+            string(&operator.lexeme),
+            expression(right)
+        ),
+        Expr::Group(inner) => format!(
+            "{{\"kind\": \"Group\", \"expression\": {}}}",
+            expression(inner)
+        ),
+        Expr::Call(callee, arguments) => {
+            let arguments: Vec<String> = arguments.iter().map(expression).collect();
+            format!(
+                "{{\"kind\": \"Call\", \"callee\": {}, \"arguments\": [{}]}}",
+                expression(callee),
+                arguments.join(", ")
+            )
+        }
+        Expr::Get(object, name) => format!(
+            "{{\"kind\": \"Get\", \"object\": {}, \"name\": {}}}",
+            expression(object),
+            string(&name.lexeme)
+        ),
+        Expr::Set(object, name, value) => format!(
+            "{{\"kind\": \"Set\", \"object\": {}, \"name\": {}, \"value\": {}}}",
+            expression(object),
+            string(&name.lexeme),
+            expression(value)
+        ),
+        Expr::List(elements) => {
+            let elements: Vec<String> = elements.iter().map(expression).collect();
+            format!(
+                "{{\"kind\": \"List\", \"elements\": [{}]}}",
+                elements.join(", ")
+            )
+        }
+        Expr::Map(_, entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{{\"key\": {}, \"value\": {}}}",
+                        expression(key),
+                        expression(value)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"kind\": \"Map\", \"entries\": [{}]}}",
+                entries.join(", ")
+            )
+        }
+        Expr::Index(object, _, index) => format!(
+            "{{\"kind\": \"Index\", \"object\": {}, \"index\": {}}}",
+            expression(object),
+            expression(index)
+        ),
+        Expr::SetIndex(object, _, index, value) => format!(
+            "{{\"kind\": \"SetIndex\", \"object\": {}, \"index\": {}, \"value\": {}}}",
+            expression(object),
+            expression(index),
+            expression(value)
+        ),
+        Expr::Assign(name, value, _) => format!(
+            "{{\"kind\": \"Assign\", \"name\": {}, \"value\": {}}}",
+            string(&name.lexeme),
+            expression(value)
+        ),
+        Expr::Compound(name, operator, value, _) => format!(
+            "{{\"kind\": \"Compound\", \"name\": {}, \"operator\": {}, \"value\": {}}}",
+            string(&name.lexeme),
+            string(&operator.lexeme),
+            expression(value)
+        ),
+        Expr::Super(method, _) => {
+            format!(
+                "{{\"kind\": \"Super\", \"method\": {}}}",
+                string(&method.lexeme)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use super::*;
+    use crate::map::Map;
+
+    #[test]
+    fn scalars_map_to_natural_json() {
+        assert_eq!(value(&Value::Number(3.0)), "3.0");
+        assert_eq!(value(&Value::Number(-0.5)), "-0.5");
+        assert_eq!(value(&Value::Boolean(true)), "true");
+        assert_eq!(
+            value(&Value::string("a \"b\"\n").unwrap()),
+            r#""a \"b\"\n""#
+        );
+        assert_eq!(value(&Value::Nil), "null");
+    }
+
+    #[test]
+    fn non_finite_numbers_become_null() {
+        assert_eq!(value(&Value::Number(f64::NAN)), "null");
+        assert_eq!(value(&Value::Number(f64::INFINITY)), "null");
+    }
+
+    #[test]
+    fn lists_and_maps_nest() {
+        let mut map = Map::default();
+        map.insert(Value::string("n").unwrap(), Value::Number(1.0));
+        map.insert(Value::Number(2.0), Value::Nil);
+        let list = Value::List(Arc::new(RwLock::new(vec![
+            Value::Boolean(false),
+            Value::Map(Arc::new(RwLock::new(map))),
+        ])));
+        assert_eq!(value(&list), r#"[false, {"n": 1.0, "2": null}]"#);
+    }
+
+    #[test]
+    fn globals_skip_natives() {
+        let entries = vec![
+            ("x".to_string(), Value::Number(3.0)),
+            (
+                "len".to_string(),
+                crate::natives::globals()
+                    .read()
+                    .unwrap()
+                    .get("len")
+                    .unwrap(),
+            ),
+        ];
+        assert_eq!(globals(&entries), "{\n  \"x\": 3.0\n}");
+    }
+}
@@ -1,96 +1,508 @@
-use std::{env as StdEnv, fs, process::ExitCode};
+use std::{
+    env as StdEnv, fs,
+    process::ExitCode,
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+#[cfg(test)]
+mod allocations;
+
+mod callable;
+
+mod config;
+
+mod diagnostics;
+use diagnostics::Diagnostic;
+
+mod json;
+
+mod map;
+
+mod optimizer;
+
+mod output;
 
 mod parser;
 use parser::Parser;
 
+mod reparse;
+
 mod env;
 use env::Env;
 
+mod natives;
+
+mod resolver;
+use resolver::Resolver;
+
 mod scanner;
 use scanner::Scanner;
 
 mod token;
+use token::{Interrupt, TokenType, Value};
+
+mod watchdog;
 
 fn main() -> ExitCode {
     let args = StdEnv::args().collect::<Vec<_>>();
-    if args.len() < 3 {
-        eprintln!("Usage: {} tokenize <filename>", args[0]);
-        return ExitCode::SUCCESS;
+    let Some(command) = args.get(1) else {
+        usage(&args[0]);
+        return ExitCode::FAILURE;
+    };
+    if !COMMANDS.contains(&command.as_str()) {
+        eprintln!("Unknown command: {command}");
+        usage(&args[0]);
+        return ExitCode::FAILURE;
     }
 
-    let command = &args[1];
-    let filename = &args[2];
+    //? Every argument up to the first flag is a file; only `run` uses more than one.
+    let filenames = args[2..]
+        .iter()
+        .take_while(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    //? Anything after `--` belongs to the script, not the interpreter.
+    let rest = &args[2 + filenames.len()..];
+    let (flags, script_args) = match rest.iter().position(|arg| arg == "--") {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => (rest, &[][..]),
+    };
+    config::set_show_types(flags.iter().any(|arg| arg == "--show-types"));
+    config::set_exit_from_result(flags.iter().any(|arg| arg == "--exit-from-result"));
+    config::set_approx_uax31(flags.iter().any(|arg| arg == "--approx-uax31"));
+    config::set_pure(flags.iter().any(|arg| arg == "--pure"));
+    config::set_strict(flags.iter().any(|arg| arg == "--strict"));
+    config::set_warn_unused(flags.iter().any(|arg| arg == "--warn-unused"));
+    config::set_optimize(flags.iter().any(|arg| arg == "--optimize"));
+    config::set_crlf(flag_value(flags, "--line-ending") == Some("crlf"));
+    config::set_entry(flag_value(flags, "--entry").map(String::from));
+    config::set_script_args(script_args.to_vec());
+    config::set_max_allocations(
+        flag_value(flags, "--max-allocations").and_then(|limit| limit.parse().ok()),
+    );
+    config::set_max_iterations(
+        flag_value(flags, "--max-iterations").and_then(|limit| limit.parse().ok()),
+    );
+    config::set_max_call_depth(
+        flag_value(flags, "--max-call-depth").and_then(|limit| limit.parse().ok()),
+    );
+    let timeout = flag_value(flags, "--timeout").and_then(|ms| ms.parse().ok());
+    let dump_env_json = flags.iter().any(|arg| arg == "--dump-env-json");
 
-    let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        eprintln!("Failed to read file {filename}");
-        String::new()
-    });
+    let files = filenames
+        .iter()
+        .map(|filename| (filename.as_str(), read_file(filename)))
+        .collect::<Vec<_>>();
+    let Some((_, file_contents)) = files.first() else {
+        eprintln!("Missing filename for '{command}'.");
+        usage(&args[0]);
+        return ExitCode::FAILURE;
+    };
 
-    let mut scanner = Scanner::new(&file_contents);
+    let mut scanner = Scanner::new(file_contents);
     match command.as_str() {
         "tokenize" => {
-            if let Err(exitcode) = scanner.tokenize() {
-                for token in scanner.tokens() {
+            scanner.tokenize();
+            let reported = report(scanner.diagnostics(), file_contents);
+            for token in scanner.tokens() {
+                if token.token_type != TokenType::Error {
                     println!("{}", token);
                 }
-                exitcode
-            } else {
-                for token in scanner.tokens() {
-                    println!("{}", token);
-                }
-                ExitCode::SUCCESS
+            }
+            match reported {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(exitcode) => exitcode,
             }
         }
-        "parse" => {
-            if let Err(exitcode) = scanner.tokenize() {
-                return exitcode;
+        "tokenize-json" => {
+            scanner.tokenize();
+            let reported = report(scanner.diagnostics(), file_contents);
+            println!("{}", json::tokens(scanner.tokens()));
+            match reported {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(exitcode) => exitcode,
             }
+        }
+        "parse" => {
+            scanner.tokenize();
             let mut parser = Parser::new(scanner.tokens());
-            if let Err(exitcode) = parser.parse() {
+            parser.parse();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
                 return exitcode;
             }
             let statements = parser.statements();
+            if config::optimize() {
+                optimizer::optimize(statements);
+            }
             for statements in statements {
                 println!("{}", statements);
             }
             ExitCode::SUCCESS
         }
-        "evaluate" => {
-            if let Err(exitcode) = scanner.tokenize() {
+        "ast" => {
+            scanner.tokenize();
+            let mut parser = Parser::new(scanner.tokens());
+            parser.parse();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
                 return exitcode;
             }
+            let statements = parser.statements();
+            if config::optimize() {
+                optimizer::optimize(statements);
+            }
+            for statement in statements {
+                println!("{}", statement.pretty(0));
+            }
+            ExitCode::SUCCESS
+        }
+        "parse-json" => {
+            scanner.tokenize();
             let mut parser = Parser::new(scanner.tokens());
-            if let Err(exitcode) = parser.parse() {
+            parser.parse();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
                 return exitcode;
             }
             let statements = parser.statements();
-            for statement in statements {
-                if let Err(exitcode) = statement.evaluate_no_run() {
-                    return exitcode;
-                }
+            if config::optimize() {
+                optimizer::optimize(statements);
             }
+            println!("{}", json::statements(statements));
             ExitCode::SUCCESS
         }
+        "evaluate" => {
+            scanner.tokenize();
+            let mut parser = Parser::new(scanner.tokens());
+            let expr = parser.parse_expression();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
+                return exitcode;
+            }
+            let mut expr = match expr {
+                Ok(expr) => expr,
+                Err(exitcode) => return exitcode,
+            };
+            optimizer::optimize_expression(&mut expr);
+            config::set_source(Some(file_contents.to_string()));
+            match expr.evaluate(natives::globals()) {
+                Ok(value) => {
+                    value.print();
+                    ExitCode::SUCCESS
+                }
+                Err(exitcode) => exitcode,
+            }
+        }
         "run" => {
-            if let Err(exitcode) = scanner.tokenize() {
+            let environment = natives::globals();
+            let exitcode = with_call_stack(|| match timeout {
+                Some(ms) => watchdog::with_timeout(Duration::from_millis(ms), || {
+                    run(&files, environment.clone())
+                }),
+                None => run(&files, environment.clone()),
+            });
+            if dump_env_json {
+                let globals = Env::root(&environment);
+                eprintln!("{}", json::globals(&globals.read().unwrap().entries()));
+            }
+            exitcode
+        }
+        "check-names" => {
+            scanner.tokenize();
+            let mut parser = Parser::new(scanner.tokens());
+            parser.parse();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
                 return exitcode;
             }
+            let mut resolver = Resolver::with_known_globals(natives::names());
+            resolver.resolve(parser.statements());
+            match report(resolver.diagnostics(), file_contents) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(exitcode) => exitcode,
+            }
+        }
+        "env-dump" => {
+            let environment = natives::globals();
+            let exitcode = with_call_stack(|| run(&files[..1], environment.clone()));
+            for (name, value) in Env::root(&environment).read().unwrap().entries() {
+                if !matches!(value, Value::Native(_)) {
+                    println!("{} = {}", name, value);
+                }
+            }
+            exitcode
+        }
+        "round-trip" => {
+            scanner.tokenize();
             let mut parser = Parser::new(scanner.tokens());
-            if let Err(exitcode) = parser.parse() {
+            parser.parse();
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            if let Err(exitcode) = report(&diagnostics, file_contents) {
                 return exitcode;
             }
-            let environment = Env::new();
-            let statements = parser.statements();
-            for statement in statements {
-                if let Err(exitcode) = statement.evaluate(environment.clone()) {
-                    return exitcode;
+            let mut exitcode = ExitCode::SUCCESS;
+            for statement in parser.statements().iter() {
+                println!("{}", reparse::reparse(statement));
+                if !reparse::round_trips(statement) {
+                    eprintln!("Statement does not round-trip: {}", statement);
+                    exitcode = ExitCode::FAILURE;
                 }
             }
-            ExitCode::SUCCESS
+            exitcode
+        }
+        "--tokens-only" => {
+            //? Streams the tokens, so only the counts are kept in memory. The total counts every
+            //? token the source spells out, unscannable ones included, but not the end marker.
+            let categories = [
+                "keywords",
+                "identifiers",
+                "literals",
+                "operators",
+                "punctuation",
+                "errors",
+            ];
+            let mut counts = [0; 6];
+            let mut errors = vec![];
+            for token in scanner.iter_tokens() {
+                match token {
+                    Ok(token) => {
+                        let category = token.token_type.category();
+                        if let Some(index) = categories.iter().position(|c| *c == category) {
+                            counts[index] += 1;
+                        }
+                    }
+                    Err(diagnostic) => {
+                        counts[5] += 1;
+                        errors.push(diagnostic);
+                    }
+                }
+            }
+            let reported = report(&errors, file_contents);
+            println!("tokens: {}", counts.iter().sum::<usize>());
+            for (category, count) in categories.iter().zip(counts) {
+                println!("{}: {}", category, count);
+            }
+            println!("lines: {}", file_contents.lines().count());
+            match reported {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(exitcode) => exitcode,
+            }
         }
-        _ => {
-            eprintln!("Unknown command: {command}");
-            ExitCode::FAILURE
+        "--teach" => {
+            println!("== Source ==");
+            println!("{}", file_contents.trim_end());
+
+            //? Every section prints even when something fails, so a student sees how far each
+            //? stage got; problems go in their own section instead of stderr.
+            scanner.tokenize();
+            println!("== Tokens ==");
+            for token in scanner.tokens() {
+                if token.token_type != TokenType::Error {
+                    println!("[line {}] {}", token.line, token);
+                }
+            }
+
+            let mut parser = Parser::new(scanner.tokens());
+            parser.parse();
+            println!("== AST ==");
+            for statement in parser.statements() {
+                println!("{}", statement.pretty(0));
+            }
+
+            let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+            println!("== Diagnostics ==");
+            if diagnostics.is_empty() {
+                println!("(none)");
+            }
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic.render(file_contents));
+            }
+            if diagnostics::has_errors(&diagnostics) {
+                ExitCode::from(65)
+            } else {
+                ExitCode::SUCCESS
+            }
         }
+        _ => unreachable!("commands are checked against COMMANDS"),
     }
 }
+
+const COMMANDS: [&str; 12] = [
+    "tokenize",
+    "tokenize-json",
+    "parse",
+    "ast",
+    "parse-json",
+    "evaluate",
+    "run",
+    "check-names",
+    "env-dump",
+    "round-trip",
+    "--tokens-only",
+    "--teach",
+];
+
+fn usage(program: &str) {
+    eprintln!("Usage: {program} <command> <filename>...");
+    eprintln!("Commands: {}", COMMANDS.join(", "));
+}
+
+fn flag_value<'a>(flags: &'a [String], name: &str) -> Option<&'a str> {
+    flags
+        .iter()
+        .position(|flag| flag == name)
+        .and_then(|index| flags.get(index + 1))
+        .map(String::as_str)
+}
+
+//? Prints each diagnostic with its snippet; any error stops the command with exit code 65,
+//? as does any warning under `--strict`.
+fn report(diagnostics: &[Diagnostic], source: &str) -> Result<(), ExitCode> {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(source));
+    }
+    if diagnostics::has_errors(diagnostics) || (config::strict() && !diagnostics.is_empty()) {
+        Err(ExitCode::from(65))
+    } else {
+        Ok(())
+    }
+}
+
+fn read_file(filename: &str) -> String {
+    fs::read_to_string(filename).unwrap_or_else(|_| {
+        eprintln!("Failed to read file {filename}");
+        String::new()
+    })
+}
+
+//? Runs each `(filename, source)` in order against one global environment, so later files
+//? see globals from earlier ones. The entry function runs once, after the last file.
+fn run(files: &[(&str, String)], environment: Arc<RwLock<Env>>) -> ExitCode {
+    let mut result = Value::Nil;
+    let mut scanner = Scanner::new("");
+    for (filename, source) in files {
+        match execute(&mut scanner, source, environment.clone()) {
+            Ok(value) => result = value,
+            Err(exitcode) => {
+                if files.len() > 1 {
+                    eprintln!("Error in {}.", filename);
+                }
+                return exitcode;
+            }
+        }
+    }
+    match config::entry() {
+        Some(entry) => match call_entry(&environment, &entry) {
+            Ok(value) => exit_code_from(&value),
+            Err(exitcode) => exitcode,
+        },
+        None if config::exit_from_result() => exit_code_from(&result),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+//? Scans, parses, resolves and evaluates one program, returning its last statement's value.
+//? Reuses `scanner` across files rather than building one per program.
+fn execute<'a>(
+    scanner: &mut Scanner<'a>,
+    source: &'a str,
+    environment: Arc<RwLock<Env>>,
+) -> Result<Value, ExitCode> {
+    scanner.reset(source);
+    scanner.tokenize();
+    let mut parser = Parser::new(scanner.tokens());
+    parser.parse();
+    let diagnostics = [scanner.diagnostics(), parser.diagnostics()].concat();
+    report(&diagnostics, source)?;
+    let statements = parser.statements();
+    //? Globals from earlier files and the natives count as declared.
+    let mut resolver = Resolver::with_known_globals(environment.read().unwrap().names());
+    resolver.resolve(statements);
+    report(resolver.diagnostics(), source)?;
+    optimizer::optimize(statements);
+    config::set_source(Some(source.to_string()));
+    let mut result = Value::Nil;
+    let out = output::stdout();
+    for statement in statements {
+        match statement.evaluate_with_writer(environment.clone(), out.clone()) {
+            Ok(value) => result = value,
+            Err(Interrupt::Return(value)) => {
+                result = value;
+                break;
+            }
+            Err(Interrupt::Error(exitcode)) => return Err(exitcode),
+            Err(Interrupt::Break | Interrupt::Continue) => {
+                unreachable!("the parser rejects loop exits outside a loop")
+            }
+        }
+    }
+    Ok(result)
+}
+
+//? Integral numbers in 0..=255 become the exit code; anything else exits with 0.
+fn exit_code_from(value: &Value) -> ExitCode {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => {
+            ExitCode::from(*n as u8)
+        }
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+//? The entry function gets the script arguments as a list of strings, or nothing if it
+//? takes no parameters.
+fn call_entry(environment: &Arc<RwLock<Env>>, entry: &str) -> Result<Value, ExitCode> {
+    let function = environment.read().unwrap().peek_global(entry);
+    let Some(Value::Function(function)) = function else {
+        eprintln!("Undefined entry function '{}'.", entry);
+        return Err(ExitCode::from(70));
+    };
+    match function.arity() {
+        0 => function.call(vec![]),
+        1 => {
+            let args = config::script_args()
+                .into_iter()
+                .map(Value::string)
+                .collect::<Result<Vec<_>, _>>()?;
+            config::track_allocation()?;
+            function.call(vec![Value::List(Arc::new(RwLock::new(args)))])
+        }
+        arity => {
+            eprintln!(
+                "Entry function '{}' takes {} parameters; expected 0 or 1.",
+                entry, arity
+            );
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? Stack reserved per Lox call; generous enough for unoptimized builds.
+const CALL_FRAME_SIZE: usize = 256 * 1024;
+
+//? Evaluation recurses on the native stack, so run it on a thread with room for
+//? `--max-call-depth` calls; going deeper is then a "Stack overflow." error, not a crash.
+fn with_call_stack(f: impl FnOnce() -> ExitCode + Send) -> ExitCode {
+    let stack_size = config::max_call_depth()
+        .saturating_mul(CALL_FRAME_SIZE)
+        .saturating_add(8 * 1024 * 1024);
+    thread::scope(|scope| {
+        match thread::Builder::new()
+            .stack_size(stack_size)
+            .spawn_scoped(scope, f)
+        {
+            Ok(interpreter) => interpreter
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+            Err(_) => {
+                eprintln!(
+                    "Cannot reserve a stack for {} nested calls.",
+                    config::max_call_depth()
+                );
+                ExitCode::FAILURE
+            }
+        }
+    })
+}
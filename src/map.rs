@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::token::Value;
+
+//? Entries keep insertion order; overwriting a key keeps its original position.
+#[derive(Debug, Default)]
+pub struct Map {
+    index: HashMap<Value, usize>,
+    entries: Vec<(Value, Value)>,
+}
+
+impl Map {
+    //? Only strings and numbers other than `NaN` can be keys; callers check this before any
+    //? other method.
+    pub fn accepts(key: &Value) -> bool {
+        match key {
+            Value::Number(n) => !n.is_nan(),
+            Value::String(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        let position = self.index.get(key)?;
+        Some(&self.entries[*position].1)
+    }
+
+    pub fn contains(&self, key: &Value) -> bool {
+        self.index.contains_key(key)
+    }
+
+    //? Returns whether the key is new. `-0` is stored as `0`, as both spell the same key.
+    pub fn insert(&mut self, key: Value, value: Value) -> bool {
+        if !Self::accepts(&key) {
+            return false;
+        }
+        match self.index.get(&key) {
+            Some(position) => {
+                self.entries[*position].1 = value;
+                false
+            }
+            None => {
+                let key = match key {
+                    Value::Number(n) => Value::Number(n + 0.0),
+                    key => key,
+                };
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                true
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+}
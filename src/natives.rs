@@ -0,0 +1,606 @@
+use std::{
+    cmp::Ordering,
+    process::{self, ExitCode},
+    sync::{Arc, RwLock},
+};
+
+use crate::{callable::Native, config, env::Env, map::Map, output, token::Value};
+
+const NATIVES: &[Native] = &[
+    Native {
+        name: "len",
+        arity: 1,
+        optional: 0,
+        function: len,
+    },
+    Native {
+        name: "push",
+        arity: 2,
+        optional: 0,
+        function: push,
+    },
+    Native {
+        name: "has",
+        arity: 2,
+        optional: 0,
+        function: has,
+    },
+    Native {
+        name: "sort_by",
+        arity: 2,
+        optional: 0,
+        function: sort_by,
+    },
+    Native {
+        name: "substr",
+        arity: 3,
+        optional: 0,
+        function: substr,
+    },
+    Native {
+        name: "charAt",
+        arity: 2,
+        optional: 0,
+        function: char_at,
+    },
+    Native {
+        name: "assert",
+        arity: 1,
+        optional: 1,
+        function: assert,
+    },
+    Native {
+        name: "str",
+        arity: 1,
+        optional: 0,
+        function: str,
+    },
+    Native {
+        name: "num",
+        arity: 1,
+        optional: 0,
+        function: num,
+    },
+    Native {
+        name: "write",
+        arity: 1,
+        optional: 0,
+        function: write,
+    },
+    Native {
+        name: "digits",
+        arity: 1,
+        optional: 0,
+        function: digits,
+    },
+    Native {
+        name: "chr",
+        arity: 1,
+        optional: 0,
+        function: chr,
+    },
+    Native {
+        name: "ord",
+        arity: 1,
+        optional: 0,
+        function: ord,
+    },
+    Native {
+        name: "abs",
+        arity: 1,
+        optional: 0,
+        function: abs,
+    },
+    Native {
+        name: "floor",
+        arity: 1,
+        optional: 0,
+        function: floor,
+    },
+    Native {
+        name: "ceil",
+        arity: 1,
+        optional: 0,
+        function: ceil,
+    },
+    Native {
+        name: "sqrt",
+        arity: 1,
+        optional: 0,
+        function: sqrt,
+    },
+    Native {
+        name: "pow",
+        arity: 2,
+        optional: 0,
+        function: pow,
+    },
+    Native {
+        name: "min",
+        arity: 2,
+        optional: usize::MAX,
+        function: min,
+    },
+    Native {
+        name: "max",
+        arity: 2,
+        optional: usize::MAX,
+        function: max,
+    },
+    Native {
+        name: "upper",
+        arity: 1,
+        optional: 0,
+        function: upper,
+    },
+    Native {
+        name: "lower",
+        arity: 1,
+        optional: 0,
+        function: lower,
+    },
+    Native {
+        name: "trim",
+        arity: 1,
+        optional: 0,
+        function: trim,
+    },
+    Native {
+        name: "split",
+        arity: 2,
+        optional: 0,
+        function: split,
+    },
+    Native {
+        name: "exit",
+        arity: 0,
+        optional: 1,
+        function: exit,
+    },
+    Native {
+        name: "typeof",
+        arity: 1,
+        optional: 0,
+        function: type_of,
+    },
+    Native {
+        name: "format",
+        arity: 1,
+        optional: usize::MAX,
+        function: format,
+    },
+];
+
+//? A fresh global environment with every native function defined, or none under `--pure`.
+pub fn globals() -> Arc<RwLock<Env>> {
+    let environment = Env::new();
+    for native in available() {
+        environment.write().unwrap().define_native(native.clone());
+    }
+    environment
+}
+
+pub fn names() -> Vec<String> {
+    available()
+        .iter()
+        .map(|native| native.name.to_string())
+        .collect()
+}
+
+fn available() -> &'static [Native] {
+    if config::pure() {
+        &[]
+    } else {
+        NATIVES
+    }
+}
+
+fn len(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+        Value::List(list) => Ok(Value::Number(list.read().unwrap().len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.read().unwrap().len() as f64)),
+        value => {
+            eprintln!(
+                "len() expects a string, list or map but got {}.",
+                value.type_name()
+            );
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? Appends in place; each push counts against the allocation cap.
+fn push(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::List(list) => {
+            config::track_allocation()?;
+            list.write().unwrap().push(arguments[1].clone());
+            Ok(Value::Nil)
+        }
+        value => {
+            eprintln!("push() expects a list but got {}.", value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+fn has(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::Map(map) if Map::accepts(&arguments[1]) => {
+            Ok(Value::Boolean(map.read().unwrap().contains(&arguments[1])))
+        }
+        Value::Map(_) => {
+            eprintln!("Map keys must be strings or numbers.");
+            Err(ExitCode::from(70))
+        }
+        value => {
+            eprintln!("has() expects a map but got {}.", value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? Sorts the list in place by the key each element maps to, keeping equal keys in their
+//? original order. The keys must all be numbers or all strings, and none may be NaN.
+fn sort_by(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let Value::List(list) = &arguments[0] else {
+        eprintln!(
+            "sort_by() expects a list but got {}.",
+            arguments[0].type_name()
+        );
+        return Err(ExitCode::from(70));
+    };
+    //? Copied out so the key function may itself read or change the list.
+    let elements = list.read().unwrap().clone();
+    let mut keyed = Vec::with_capacity(elements.len());
+    for element in elements {
+        let key = call_unary("sort_by", &arguments[1], element.clone())?;
+        keyed.push((key, element));
+    }
+    if let Some((first, _)) = keyed.first() {
+        for (key, _) in &keyed {
+            if key.partial_cmp(key).is_none() {
+                eprintln!("sort_by() keys must not be NaN.");
+                return Err(ExitCode::from(70));
+            }
+            if key.partial_cmp(first).is_none() {
+                eprintln!(
+                    "sort_by() keys must be mutually comparable but got {} and {}.",
+                    first.type_name(),
+                    key.type_name()
+                );
+                return Err(ExitCode::from(70));
+            }
+        }
+    }
+    keyed.sort_by(|(left, _), (right, _)| left.partial_cmp(right).unwrap());
+    *list.write().unwrap() = keyed.into_iter().map(|(_, element)| element).collect();
+    Ok(Value::Nil)
+}
+
+fn call_unary(name: &str, callee: &Value, argument: Value) -> Result<Value, ExitCode> {
+    match callee {
+        Value::Function(function)
+            if function.arity() == 1 || (function.arity() == 0 && function.rest.is_some()) =>
+        {
+            function.call(vec![argument])
+        }
+        Value::Native(native)
+            if (native.arity..=native.arity.saturating_add(native.optional)).contains(&1) =>
+        {
+            (native.function)(&[argument])
+        }
+        value => {
+            eprintln!(
+                "{}() expects a function of one argument but got {}.",
+                name,
+                value.type_name()
+            );
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? The message is optional; without one only "Assertion failed." is printed.
+fn assert(arguments: &[Value]) -> Result<Value, ExitCode> {
+    if arguments[0].is_truthy() {
+        return Ok(Value::Nil);
+    }
+    match arguments.get(1) {
+        Some(message) => eprintln!("Assertion failed: {}", message),
+        None => eprintln!("Assertion failed."),
+    }
+    Err(ExitCode::from(70))
+}
+
+//? Ends the process right away with the code truncated and clamped to 0..=255; no code
+//? means 0.
+fn exit(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let code = match arguments.first() {
+        None => 0,
+        Some(Value::Number(n)) => n.trunc().clamp(0.0, 255.0) as i32,
+        Some(value) => {
+            eprintln!("exit() expects a number but got {}.", value.type_name());
+            return Err(ExitCode::from(70));
+        }
+    };
+    let _ = output::flush();
+    process::exit(code)
+}
+
+fn type_of(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Value::string(arguments[0].type_name())
+}
+
+//? Each `{}` takes the next argument; `{{` and `}}` stand for literal braces. Every
+//? argument must fill exactly one placeholder.
+fn format(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let template = string_argument("format", &arguments[0])?;
+    let mut values = arguments[1..].iter();
+    let mut placeholders = 0;
+    let mut formatted = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(value) = values.next() {
+                    formatted.push_str(&value.to_string());
+                }
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            _ => formatted.push(c),
+        }
+    }
+    if placeholders != arguments.len() - 1 {
+        eprintln!(
+            "format() has {} placeholders but got {} values.",
+            placeholders,
+            arguments.len() - 1
+        );
+        return Err(ExitCode::from(70));
+    }
+    Value::string(formatted)
+}
+
+fn str(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Value::string(arguments[0].to_string())
+}
+
+//? A string that isn't a finite number yields nil so scripts can test the result;
+//? passing a non-string is still a runtime error.
+fn num(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::String(s) => Ok(match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Value::Number(n),
+            _ => Value::Nil,
+        }),
+        value => {
+            eprintln!("num() expects a string but got {}.", value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? Like `print` without the line ending; flushed so partial lines show up immediately.
+fn write(arguments: &[Value]) -> Result<Value, ExitCode> {
+    if output::write(format_args!("{}", arguments[0])).is_err() || output::flush().is_err() {
+        eprintln!("write() failed to write to stdout.");
+        return Err(ExitCode::from(70));
+    }
+    Ok(Value::Nil)
+}
+
+//? Decimal digits of an integral number, ignoring the sign.
+fn digits(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::Number(n) if n.is_finite() && n.fract() == 0.0 => {
+            Ok(Value::Number(format!("{:.0}", n.abs()).len() as f64))
+        }
+        Value::Number(_) => {
+            eprintln!("digits() expects an integer but got {}.", arguments[0]);
+            Err(ExitCode::from(70))
+        }
+        value => {
+            eprintln!("digits() expects a number but got {}.", value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+fn chr(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let code_point = match &arguments[0] {
+        Value::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= u32::MAX as f64 => {
+            char::from_u32(*n as u32)
+        }
+        Value::Number(_) => None,
+        value => {
+            eprintln!("chr() expects a number but got {}.", value.type_name());
+            return Err(ExitCode::from(70));
+        }
+    };
+    match code_point {
+        Some(c) => Value::string(c),
+        None => {
+            eprintln!("chr() got invalid code point {}.", arguments[0]);
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? `start` and `len` count chars, not bytes; the slice must lie within the string.
+fn substr(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("substr", &arguments[0])?;
+    let start = index_argument("substr", &arguments[1])?;
+    let len = index_argument("substr", &arguments[2])?;
+    let count = s.chars().count();
+    let end = start.saturating_add(len);
+    if end > count {
+        eprintln!(
+            "substr() range {}..{} out of range for string of length {}.",
+            start, end, count
+        );
+        return Err(ExitCode::from(70));
+    }
+    Value::string(s.chars().skip(start).take(len).collect::<String>())
+}
+
+fn char_at(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("charAt", &arguments[0])?;
+    let index = index_argument("charAt", &arguments[1])?;
+    match s.chars().nth(index) {
+        Some(c) => Value::string(c),
+        None => {
+            eprintln!(
+                "charAt() index {} out of range for string of length {}.",
+                index,
+                s.chars().count()
+            );
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+fn abs(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Ok(Value::Number(number_argument("abs", &arguments[0])?.abs()))
+}
+
+fn floor(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Ok(Value::Number(
+        number_argument("floor", &arguments[0])?.floor(),
+    ))
+}
+
+fn ceil(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Ok(Value::Number(
+        number_argument("ceil", &arguments[0])?.ceil(),
+    ))
+}
+
+//? Negative numbers give NaN, as in IEEE arithmetic, rather than an error.
+fn sqrt(arguments: &[Value]) -> Result<Value, ExitCode> {
+    Ok(Value::Number(
+        number_argument("sqrt", &arguments[0])?.sqrt(),
+    ))
+}
+
+fn pow(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let base = number_argument("pow", &arguments[0])?;
+    let exponent = number_argument("pow", &arguments[1])?;
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn min(arguments: &[Value]) -> Result<Value, ExitCode> {
+    extreme("min", arguments, Ordering::Less)
+}
+
+fn max(arguments: &[Value]) -> Result<Value, ExitCode> {
+    extreme("max", arguments, Ordering::Greater)
+}
+
+//? Keeps the first argument that orders `wanted` against every other; a `NaN` compares as
+//? neither, so it only wins when it comes first.
+fn extreme(name: &str, arguments: &[Value], wanted: Ordering) -> Result<Value, ExitCode> {
+    for argument in arguments {
+        number_argument(name, argument)?;
+    }
+    let mut best = &arguments[0];
+    for argument in &arguments[1..] {
+        if argument.partial_cmp(best) == Some(wanted) {
+            best = argument;
+        }
+    }
+    Ok(best.clone())
+}
+
+fn number_argument(name: &str, value: &Value) -> Result<f64, ExitCode> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        value => {
+            eprintln!("{}() expects a number but got {}.", name, value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+fn upper(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("upper", &arguments[0])?;
+    Value::string(s.to_uppercase())
+}
+
+fn lower(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("lower", &arguments[0])?;
+    Value::string(s.to_lowercase())
+}
+
+fn trim(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("trim", &arguments[0])?;
+    Value::string(s.trim().to_string())
+}
+
+//? An empty separator splits into single characters.
+fn split(arguments: &[Value]) -> Result<Value, ExitCode> {
+    let s = string_argument("split", &arguments[0])?;
+    let separator = string_argument("split", &arguments[1])?;
+    let parts = if separator.is_empty() {
+        s.chars().map(Value::string).collect::<Result<_, _>>()?
+    } else {
+        s.split(separator)
+            .map(Value::string)
+            .collect::<Result<_, _>>()?
+    };
+    config::track_allocation()?;
+    Ok(Value::List(Arc::new(RwLock::new(parts))))
+}
+
+fn string_argument<'a>(name: &str, value: &'a Value) -> Result<&'a str, ExitCode> {
+    match value {
+        Value::String(s) => Ok(s.as_str()),
+        value => {
+            eprintln!("{}() expects a string but got {}.", name, value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+//? Negative indices are errors rather than counting from the end.
+fn index_argument(name: &str, value: &Value) -> Result<usize, ExitCode> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && *n >= 0.0 => Ok(*n as usize),
+        Value::Number(_) => {
+            eprintln!(
+                "{}() expects a non-negative integer index but got {}.",
+                name, value
+            );
+            Err(ExitCode::from(70))
+        }
+        value => {
+            eprintln!("{}() expects a number but got {}.", name, value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
+
+fn ord(arguments: &[Value]) -> Result<Value, ExitCode> {
+    match &arguments[0] {
+        Value::String(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Value::Number(c as u32 as f64)),
+                _ => {
+                    eprintln!("ord() expects a single character but got \"{}\".", s);
+                    Err(ExitCode::from(70))
+                }
+            }
+        }
+        value => {
+            eprintln!("ord() expects a string but got {}.", value.type_name());
+            Err(ExitCode::from(70))
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::config;
+use crate::token::{Expr, Stmt, Token, TokenType};
+
+//? Rewrites the tree before evaluation; `parse` still prints the tree as written unless
+//? `--optimize` asks for constant folding, which it then shows.
+pub fn optimize(stmts: &mut [Stmt]) {
+    for stmt in stmts {
+        optimize_statement(stmt);
+    }
+}
+
+fn optimize_statement(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Print(exprs) => {
+            for expr in exprs {
+                optimize_expression(expr);
+            }
+        }
+        Stmt::Block(stmts) | Stmt::Class(_, _, stmts) => optimize(stmts),
+        //? Bodies are only shared once functions are created at runtime, so nothing is copied.
+        Stmt::Function(_, _, _, body) => optimize(Arc::make_mut(body).as_mut_slice()),
+        Stmt::Declare(_, Some(stmt), _) | Stmt::Assign(_, stmt, _) => optimize_statement(stmt),
+        Stmt::Declare(_, None, _) => {}
+        Stmt::While(condition, body)
+        | Stmt::DoWhile(body, condition)
+        | Stmt::Repeat(condition, body) => {
+            optimize_statement(condition);
+            optimize_statement(body);
+        }
+        Stmt::For(init, condition, increment, body) => {
+            for clause in [init, condition, increment].into_iter().flatten() {
+                optimize_statement(clause);
+            }
+            optimize_statement(body);
+        }
+        Stmt::If(condition, if_branch, else_branch) => {
+            optimize_statement(condition);
+            optimize_statement(if_branch);
+            if let Some(else_branch) = else_branch {
+                optimize_statement(else_branch);
+            }
+        }
+        Stmt::Switch(value, cases, default) => {
+            optimize_expression(value);
+            for (case, body) in cases {
+                optimize_expression(case);
+                optimize(body);
+            }
+            if let Some(default) = default {
+                optimize(default);
+            }
+        }
+        Stmt::Return(Some(expr)) | Stmt::Expr(expr) => optimize_expression(expr),
+        Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+pub fn optimize_expression(expr: &mut Expr) {
+    match expr {
+        Expr::Binary(left, _, right) => {
+            optimize_expression(left);
+            optimize_expression(right);
+        }
+        Expr::Unary(_, right)
+        | Expr::Group(right)
+        | Expr::Get(right, _)
+        | Expr::Assign(_, right, _)
+        | Expr::Compound(_, _, right, _) => optimize_expression(right),
+        Expr::Call(callee, arguments) => {
+            optimize_expression(callee);
+            for argument in arguments {
+                optimize_expression(argument);
+            }
+        }
+        Expr::List(elements) => {
+            for element in elements {
+                optimize_expression(element);
+            }
+        }
+        Expr::Map(_, entries) => {
+            for (key, value) in entries {
+                optimize_expression(key);
+                optimize_expression(value);
+            }
+        }
+        Expr::Index(object, _, index) => {
+            optimize_expression(object);
+            optimize_expression(index);
+        }
+        Expr::SetIndex(object, _, index, value) => {
+            optimize_expression(object);
+            optimize_expression(index);
+            optimize_expression(value);
+        }
+        Expr::Set(object, _, value) => {
+            optimize_expression(object);
+            optimize_expression(value);
+        }
+        Expr::Literal(_) | Expr::Variable(_, _) | Expr::Super(_, _) => {}
+    }
+
+    if let Some(folded) = fold_negation(expr) {
+        *expr = folded;
+    }
+    if config::optimize() {
+        if let Some(folded) = fold_constant(expr) {
+            *expr = folded;
+        }
+    }
+}
+
+//? `2 + 3` becomes the literal `5`, and `(5)` just `5`, so nested constants fold bottom-up.
+//? Only number and string operands are folded, and only where evaluating can't fail or
+//? produce a non-finite number; anything else is left for runtime.
+fn fold_constant(expr: &Expr) -> Option<Expr> {
+    let (left, operator, right) = match expr {
+        Expr::Group(inner) if matches!(inner.as_ref(), Expr::Literal(_)) => {
+            return Some(inner.as_ref().clone());
+        }
+        Expr::Binary(left, operator, right) => (left, operator, right),
+        _ => return None,
+    };
+    let (Expr::Literal(left), Expr::Literal(right)) = (left.as_ref(), right.as_ref()) else {
+        return None;
+    };
+    let token_type = match (&left.token_type, &right.token_type) {
+        (TokenType::Number(a), TokenType::Number(b)) => match operator.token_type {
+            TokenType::Plus => TokenType::Number(a + b),
+            TokenType::Minus => TokenType::Number(a - b),
+            TokenType::Star => TokenType::Number(a * b),
+            TokenType::Slash => TokenType::Number(a / b),
+            //? Modulo by zero is a runtime error, so it must not fold away.
+            TokenType::Percent if *b == 0.0 => return None,
+            TokenType::Percent => TokenType::Number(a % b),
+            TokenType::StarStar => TokenType::Number(a.powf(*b)),
+            _ => compare(&operator.token_type, a.partial_cmp(b))?,
+        },
+        (TokenType::String(a), TokenType::String(b)) => match operator.token_type {
+            TokenType::Plus => TokenType::String(a.to_owned() + b),
+            _ => compare(&operator.token_type, a.partial_cmp(b))?,
+        },
+        _ => return None,
+    };
+    let lexeme = match &token_type {
+        TokenType::Number(n) if !n.is_finite() => return None,
+        TokenType::Number(n) => n.to_string(),
+        TokenType::String(s) => s.clone(),
+        TokenType::True => "true".to_string(),
+        _ => "false".to_string(),
+    };
+    Some(Expr::Literal(Token {
+        token_type,
+        lexeme,
+        line: operator.line,
+        column: operator.column,
+    }))
+}
+
+//? The boolean literal a comparison or equality operator gives for `ordering`.
+fn compare(operator: &TokenType, ordering: Option<Ordering>) -> Option<TokenType> {
+    let result = match operator {
+        TokenType::Greater => ordering == Some(Ordering::Greater),
+        TokenType::GreaterEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        TokenType::Less => ordering == Some(Ordering::Less),
+        TokenType::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        TokenType::EqualEqual => ordering == Some(Ordering::Equal),
+        TokenType::BangEqual => ordering != Some(Ordering::Equal),
+        _ => return None,
+    };
+    Some(if result {
+        TokenType::True
+    } else {
+        TokenType::False
+    })
+}
+
+//? `-5` becomes the literal `-5` instead of negating `5` at runtime; `-x` is left alone.
+fn fold_negation(expr: &Expr) -> Option<Expr> {
+    let Expr::Unary(operator, right) = expr else {
+        return None;
+    };
+    let Expr::Literal(literal) = right.as_ref() else {
+        return None;
+    };
+    match (&operator.token_type, &literal.token_type) {
+        (TokenType::Minus, TokenType::Number(n)) => Some(Expr::Literal(Token {
+            token_type: TokenType::Number(-n),
+            lexeme: format!("-{}", literal.lexeme),
+            line: operator.line,
+            column: operator.column,
+        })),
+        _ => None,
+    }
+}
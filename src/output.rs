@@ -0,0 +1,41 @@
+use std::{
+    cell::RefCell,
+    fmt::Arguments,
+    io::{self, Write},
+    rc::Rc,
+};
+
+//? Where program output goes; shared so the caller can read it back once evaluation is done.
+pub type Sink = Rc<RefCell<dyn Write>>;
+
+thread_local! {
+    //? While set, program output goes here instead of straight to stdout.
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+pub fn stdout() -> Sink {
+    Rc::new(RefCell::new(io::stdout()))
+}
+
+//? Everything a program prints goes through here so it can be redirected.
+pub fn write(args: Arguments) -> io::Result<()> {
+    SINK.with(|sink| match sink.borrow().as_ref() {
+        Some(sink) => sink.borrow_mut().write_fmt(args),
+        None => io::stdout().write_fmt(args),
+    })
+}
+
+pub fn flush() -> io::Result<()> {
+    SINK.with(|sink| match sink.borrow().as_ref() {
+        Some(sink) => sink.borrow_mut().flush(),
+        None => io::stdout().flush(),
+    })
+}
+
+//? Runs `f` with output sent to `sink`, then puts back whatever was there before.
+pub fn with_sink<T>(sink: Sink, f: impl FnOnce() -> T) -> T {
+    let previous = SINK.with(|current| current.replace(Some(sink)));
+    let result = f();
+    SINK.with(|current| current.replace(previous));
+    result
+}
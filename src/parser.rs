@@ -1,12 +1,22 @@
-use std::process::ExitCode;
+use std::{process::ExitCode, sync::Arc};
 
+use crate::diagnostics::{Diagnostic, Diagnostics};
 use crate::token::{Expr, Stmt, Token, TokenType};
 
 pub struct Parser<'a> {
     tokens: &'a [Token],
     stmts: Vec<Stmt>,
     current: usize,
-    reporter: ErrorReporter,
+    classes: Vec<ClassKind>,
+    //? Loop bodies enclosing the current statement, reset inside each function body.
+    loops: usize,
+    diagnostics: Diagnostics,
+}
+
+#[derive(PartialEq)]
+enum ClassKind {
+    Class,
+    Subclass,
 }
 
 impl<'a> Parser<'a> {
@@ -15,7 +25,9 @@ impl<'a> Parser<'a> {
             tokens,
             stmts: vec![],
             current: 0,
-            reporter: ErrorReporter::new(),
+            classes: vec![],
+            loops: 0,
+            diagnostics: Diagnostics::default(),
         }
     }
 
@@ -23,33 +35,99 @@ impl<'a> Parser<'a> {
         &mut self.stmts
     }
 
-    pub fn parse(&mut self) -> Result<(), ExitCode> {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.as_slice()
+    }
+
+    pub fn parse(&mut self) {
         while !self.is_eof() {
-            if let Ok(stmt) = self.parse_statement() {
+            if let Ok(Some(stmt)) = self.recovering_statement() {
                 self.stmts.push(stmt);
             }
         }
-        if self.reporter.had_error {
-            Err(ExitCode::from(65))
-        } else {
-            Ok(())
+    }
+
+    //? A statement that broke on an `Error` token is skipped up to its end, giving `None`,
+    //? so the scanner's error isn't followed by parse errors about the same text.
+    fn recovering_statement(&mut self) -> Result<Option<Stmt>, ()> {
+        match self.parse_statement() {
+            Ok(stmt) => Ok(Some(stmt)),
+            Err(()) if self.previous().token_type == TokenType::Error => {
+                self.synchronize();
+                Ok(None)
+            }
+            Err(()) => Err(()),
+        }
+    }
+
+    fn synchronize(&mut self) {
+        while !self.is_eof() && self.previous().token_type != TokenType::SemiColon {
+            if [
+                TokenType::Class,
+                TokenType::Fun,
+                TokenType::Var,
+                TokenType::Const,
+                TokenType::For,
+                TokenType::If,
+                TokenType::While,
+                TokenType::Print,
+                TokenType::Return,
+            ]
+            .iter()
+            .any(|t| self.check(t))
+            {
+                return;
+            }
+            self.advance();
+        }
+    }
+
+    //? Parses the whole input as a single expression, as `evaluate` does. On failure the
+    //? errors are in `diagnostics`.
+    pub fn parse_expression(&mut self) -> Result<Expr, ExitCode> {
+        let expr = self.express().map_err(|()| ExitCode::from(65))?;
+        if !self.is_eof() {
+            let token = &self.tokens[self.current];
+            self.diagnostics.error(token, "Expect end of expression.");
+            return Err(ExitCode::from(65));
         }
+        Ok(expr)
     }
 
     fn parse_statement(&mut self) -> Result<Stmt, ()> {
-        if self.match_tokens(&[TokenType::LeftBrace]) {
+        if self.starts_map() {
+            self.expression_statement()
+        } else if self.match_tokens(&[TokenType::LeftBrace]) {
             self.block_statement()
         } else if self.match_tokens(&[TokenType::Print]) {
             self.print_statement()
         } else if self.match_tokens(&[TokenType::While]) {
             self.while_statement()
+        } else if self.match_tokens(&[TokenType::Do]) {
+            self.do_while_statement()
+        } else if self.match_tokens(&[TokenType::Repeat]) {
+            self.repeat_statement()
         } else if self.match_tokens(&[TokenType::For]) {
             self.for_statement()
         } else if self.match_tokens(&[TokenType::If]) {
             self.if_statement()
+        } else if self.match_tokens(&[TokenType::Switch]) {
+            self.switch_statement()
         } else if self.match_tokens(&[TokenType::Var]) {
-            self.declare_statement()
-        } else if self.match_tokens(&[TokenType::Identifier]) {
+            self.declare_statement(false)
+        } else if self.match_tokens(&[TokenType::Const]) {
+            self.declare_statement(true)
+        } else if self.match_tokens(&[TokenType::Fun]) {
+            self.function("function")
+        } else if self.match_tokens(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.match_tokens(&[TokenType::Return]) {
+            self.return_statement()
+        } else if self.match_tokens(&[TokenType::Break]) {
+            self.loop_exit(Stmt::Break)
+        } else if self.match_tokens(&[TokenType::Continue]) {
+            self.loop_exit(Stmt::Continue)
+        } else if self.check(&TokenType::Identifier) {
             self.assign_statement()
         } else {
             self.expression_statement()
@@ -58,20 +136,40 @@ impl<'a> Parser<'a> {
 
     fn block_statement(&mut self) -> Result<Stmt, ()> {
         let mut stmts = vec![];
+        let mut returned = false;
         while !self.check(&TokenType::RightBrace) && !self.is_eof() {
-            stmts.push(self.parse_statement()?);
+            //? Only the first statement after a `return` is reported.
+            if !returned && matches!(stmts.last(), Some(Stmt::Return(_))) {
+                returned = true;
+                let token = &self.tokens[self.current];
+                self.diagnostics.warning(token, "Unreachable code.");
+            }
+            stmts.extend(self.recovering_statement()?);
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' .")?;
         Ok(Stmt::Block(stmts))
     }
 
+    //? A top-level comma separates printed values; `print (a, b)` still prints just `b`.
     fn print_statement(&mut self) -> Result<Stmt, ()> {
-        let stmt = self.parse_statement()?;
+        let expr = self.express()?;
         if self.peek().token_type == TokenType::SemiColon {
             self.consume(TokenType::SemiColon, "")?;
         }
-        Ok(Stmt::Print(Box::new(stmt)))
+        let mut exprs = vec![];
+        Self::unsequence(expr, &mut exprs);
+        Ok(Stmt::Print(exprs))
+    }
+
+    fn unsequence(expr: Expr, exprs: &mut Vec<Expr>) {
+        match expr {
+            Expr::Binary(left, operator, right) if operator.token_type == TokenType::Comma => {
+                Self::unsequence(*left, exprs);
+                exprs.push(*right);
+            }
+            expr => exprs.push(expr),
+        }
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ()> {
@@ -79,18 +177,39 @@ impl<'a> Parser<'a> {
         let condition = self.parse_statement()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
 
-        let body = self.parse_statement()?;
+        let body = self.loop_body()?;
         Ok(Stmt::While(Box::new(condition), Box::new(body)))
     }
 
+    fn do_while_statement(&mut self) -> Result<Stmt, ()> {
+        let body = self.loop_body()?;
+        self.consume(TokenType::While, "Expect 'while' after do body.")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.parse_statement()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        if self.peek().token_type == TokenType::SemiColon {
+            self.consume(TokenType::SemiColon, "")?;
+        }
+        Ok(Stmt::DoWhile(Box::new(body), Box::new(condition)))
+    }
+
+    fn repeat_statement(&mut self) -> Result<Stmt, ()> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'repeat'.")?;
+        let count = self.parse_statement()?;
+        self.consume(TokenType::RightParen, "Expect ')' after repeat count.")?;
+
+        let body = self.loop_body()?;
+        Ok(Stmt::Repeat(Box::new(count), Box::new(body)))
+    }
+
     fn for_statement(&mut self) -> Result<Stmt, ()> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.match_tokens(&[TokenType::SemiColon]) {
             None
         } else if self.match_tokens(&[TokenType::Var]) {
-            Some(self.declare_statement()?)
-        } else if self.match_tokens(&[TokenType::Identifier]) {
+            Some(self.declare_statement(false)?)
+        } else if self.check(&TokenType::Identifier) {
             Some(self.assign_statement()?)
         } else {
             Some(self.parse_statement()?)
@@ -99,8 +218,7 @@ impl<'a> Parser<'a> {
         if let Some(Stmt::Block(ref stmts)) = initializer {
             if stmts.is_empty() {
                 let token = self.previous();
-                self.reporter
-                    .error(token.line, &token.lexeme, "Expect expression.");
+                self.diagnostics.error(token, "Expect expression.");
             }
         }
 
@@ -113,8 +231,7 @@ impl<'a> Parser<'a> {
         if let Some(Stmt::Block(ref stmts)) = condition {
             if stmts.is_empty() {
                 let token = self.previous();
-                self.reporter
-                    .error(token.line, &token.lexeme, "Expect expression.");
+                self.diagnostics.error(token, "Expect expression.");
             }
         }
 
@@ -129,17 +246,15 @@ impl<'a> Parser<'a> {
         if let Some(Stmt::Block(ref stmts)) = increment {
             if stmts.is_empty() {
                 let token = self.previous();
-                self.reporter
-                    .error(token.line, &token.lexeme, "Expect expression.");
+                self.diagnostics.error(token, "Expect expression.");
             }
         }
 
-        let body = self.parse_statement()?;
+        let body = self.loop_body()?;
 
-        if let Stmt::Declare(_, _) = body {
+        if let Stmt::Declare(..) = body {
             let token = &self.tokens[self.current - 3];
-            self.reporter
-                .error(token.line, &token.lexeme, "Expect expression.");
+            self.diagnostics.error(token, "Expect expression.");
         }
 
         Ok(Stmt::For(
@@ -150,6 +265,31 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    fn loop_body(&mut self) -> Result<Stmt, ()> {
+        self.loops += 1;
+        let body = self.parse_statement();
+        self.loops -= 1;
+        body
+    }
+
+    //? `break` and `continue` apply to the innermost loop around them in the same function.
+    fn loop_exit(&mut self, stmt: Stmt) -> Result<Stmt, ()> {
+        let keyword = self.previous();
+        if self.loops == 0 {
+            self.diagnostics.error(
+                keyword,
+                &format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            );
+            return Err(());
+        }
+        if self.peek().token_type == TokenType::SemiColon {
+            self.consume(TokenType::SemiColon, "")?;
+        }
+        Ok(stmt)
+    }
+
+    //? The `else` check runs as soon as the inner branch is parsed, so a dangling `else`
+    //? binds to the nearest `if`, and `else if` chains need no braces.
     fn if_statement(&mut self) -> Result<Stmt, ()> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.parse_statement()?;
@@ -168,53 +308,212 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn declare_statement(&mut self) -> Result<Stmt, ()> {
+    fn switch_statement(&mut self) -> Result<Stmt, ()> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'switch'.")?;
+        let value = self.express()?;
+        self.consume(TokenType::RightParen, "Expect ')' after switch value.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before switch body.")?;
+
+        let mut cases = vec![];
+        while self.match_tokens(&[TokenType::Case]) {
+            let case = self.express()?;
+            self.consume(TokenType::Colon, "Expect ':' after case value.")?;
+            cases.push((case, self.switch_arm()?));
+        }
+        let default = if self.match_tokens(&[TokenType::Default]) {
+            self.consume(TokenType::Colon, "Expect ':' after 'default'.")?;
+            Some(self.switch_arm()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::RightBrace, "Expect '}' after switch body.")?;
+
+        Ok(Stmt::Switch(value, cases, default))
+    }
+
+    //? An arm runs until the next `case`, `default` or the closing brace.
+    fn switch_arm(&mut self) -> Result<Vec<Stmt>, ()> {
+        let mut stmts = vec![];
+        while ![TokenType::Case, TokenType::Default, TokenType::RightBrace]
+            .iter()
+            .any(|t| self.check(t))
+            && !self.is_eof()
+        {
+            stmts.push(self.parse_statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn declare_statement(&mut self, constant: bool) -> Result<Stmt, ()> {
+        if self.check(&TokenType::Error) {
+            self.advance();
+            return Err(());
+        }
         if !self.check(&TokenType::Identifier) {
             let token = self.previous();
-            self.reporter
-                .error(token.line, &token.lexeme, "Expect expression.");
+            self.diagnostics.error(token, "Expect expression.");
             return Err(());
         }
 
         let var = self.consume(TokenType::Identifier, "Expect variable name.")?;
         let stmt = if self.match_tokens(&[TokenType::Equal]) {
-            self.parse_statement()?
+            self.initializer()?
+        } else if constant {
+            let token = self.peek().clone();
+            self.diagnostics
+                .error(&token, "Expect '=' after constant name.");
+            return Err(());
         } else {
             if self.peek().token_type == TokenType::SemiColon {
                 self.consume(TokenType::SemiColon, "")?;
             }
-            return Ok(Stmt::Declare(
-                var.lexeme,
-                Box::new(Stmt::Expr(Expr::Literal(Token {
-                    token_type: TokenType::Nil,
-                    lexeme: "nil".to_string(),
-                    line: self.previous().line,
-                }))),
-            ));
+            return Ok(Stmt::Declare(var.clone(), None, false));
         };
 
-        Ok(Stmt::Declare(var.lexeme, Box::new(stmt)))
+        Ok(Stmt::Declare(var.clone(), Some(Box::new(stmt)), constant))
     }
 
-    fn assign_statement(&mut self) -> Result<Stmt, ()> {
-        let var = self.previous();
-        match self.peek().token_type {
-            TokenType::SemiColon => {
-                self.consume(TokenType::SemiColon, "")?;
-                Ok(Stmt::Expr(Expr::Literal(var)))
+    fn function(&mut self, kind: &str) -> Result<Stmt, ()> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name."))?;
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+
+        let mut params = vec![];
+        let mut rest = None;
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if self.match_tokens(&[TokenType::DotDotDot]) {
+                    let param = self.consume(TokenType::Identifier, "Expect parameter name.")?;
+                    rest = Some(param.lexeme.clone());
+                    break;
+                }
+                let param = self.consume(TokenType::Identifier, "Expect parameter name.")?;
+                params.push(param.lexeme.clone());
+                if !self.match_tokens(&[TokenType::Comma]) || self.check(&TokenType::RightParen) {
+                    break;
+                }
             }
-            TokenType::Equal => {
-                self.consume(TokenType::Equal, "")?;
-                let stmt = self.parse_statement()?;
-                Ok(Stmt::Assign(var.lexeme, Box::new(stmt)))
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.block_statement();
+        self.loops = loops;
+        let Stmt::Block(body) = body? else {
+            unreachable!()
+        };
+
+        Ok(Stmt::Function(
+            name.lexeme.clone(),
+            params,
+            rest,
+            Arc::new(body),
+        ))
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ()> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let superclass = if self.match_tokens(&[TokenType::Less]) {
+            let superclass = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            if superclass.lexeme == name.lexeme {
+                self.diagnostics
+                    .error(superclass, "A class can't inherit from itself.");
+                return Err(());
+            }
+            Some(Expr::Variable(superclass.clone(), None))
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+        self.classes.push(if superclass.is_some() {
+            ClassKind::Subclass
+        } else {
+            ClassKind::Class
+        });
+        while !self.check(&TokenType::RightBrace) && !self.is_eof() {
+            match self.function("method") {
+                Ok(method) => methods.push(method),
+                Err(()) => {
+                    self.classes.pop();
+                    return Err(());
+                }
             }
-            _ => {
-                self.retreat();
-                Ok(self.expression_statement()?)
+        }
+        self.classes.pop();
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::Class(name.lexeme.clone(), superclass, methods))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ()> {
+        let value = if self.check(&TokenType::SemiColon) || self.check(&TokenType::RightBrace) {
+            None
+        } else {
+            Some(self.express()?)
+        };
+        if self.peek().token_type == TokenType::SemiColon {
+            self.consume(TokenType::SemiColon, "")?;
+        }
+        Ok(Stmt::Return(value))
+    }
+
+    //? Starts at an identifier without consuming it, so anything other than `x = ...` is
+    //? parsed as an ordinary expression statement from the identifier on.
+    fn assign_statement(&mut self) -> Result<Stmt, ()> {
+        let next = self
+            .tokens
+            .get(self.current + 1)
+            .map(|token| &token.token_type);
+        match next {
+            Some(TokenType::Equal) => {
+                let var = self.advance();
+                self.advance();
+                let value = self.assignment()?;
+                //? The comma binds looser than `=`, so `a = 1, b = 2` assigns only `1` to `a`.
+                let stmt = if self.check(&TokenType::Comma) {
+                    let assign = Expr::Assign(var.clone(), Box::new(value), None);
+                    Stmt::Expr(self.sequence(assign)?)
+                } else {
+                    Stmt::Assign(var.lexeme.clone(), Box::new(Stmt::Expr(value)), None)
+                };
+                if self.peek().token_type == TokenType::SemiColon {
+                    self.consume(TokenType::SemiColon, "")?;
+                }
+                Ok(stmt)
             }
+            _ => self.expression_statement(),
         }
     }
 
+    //? After `=`, a `{` always opens a map literal rather than a block.
+    fn initializer(&mut self) -> Result<Stmt, ()> {
+        if self.check(&TokenType::LeftBrace) {
+            self.expression_statement()
+        } else {
+            self.parse_statement()
+        }
+    }
+
+    //? Elsewhere `{` opens a block unless it reads `{ "key":` or `{ 1:`.
+    fn starts_map(&self) -> bool {
+        let [brace, key, colon, ..] = &self.tokens[self.current..] else {
+            return false;
+        };
+        brace.token_type == TokenType::LeftBrace
+            && matches!(key.token_type, TokenType::String(_) | TokenType::Number(_))
+            && colon.token_type == TokenType::Colon
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, ()> {
         let expr = self.express()?;
         if self.peek().token_type == TokenType::SemiColon {
@@ -231,118 +530,172 @@ impl<'a> Parser<'a> {
         self.peek().token_type == TokenType::Eof
     }
 
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &'a Token {
         if !self.is_eof() {
             self.current += 1;
         }
-        self.tokens[self.current - 1].clone()
-    }
-
-    fn retreat(&mut self) {
-        if self.current > 0 {
-            self.current -= 1;
-        }
+        //? With nothing but `EOF` left from the start, the end is all there is to return.
+        &self.tokens[self.current.saturating_sub(1)]
     }
 
+    //? The comma operator binds loosest, below assignment. Argument lists, list literals
+    //? and map entries parse their items with `assignment` so their commas stay separators.
     fn express(&mut self) -> Result<Expr, ()> {
-        self.or()
+        let first = self.assignment()?;
+        self.sequence(first)
     }
 
-    fn or(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.and()?;
-
-        while self.match_tokens(&[TokenType::Or]) {
-            let operator = self.previous();
-            let right = self.or()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right))
+    fn sequence(&mut self, mut expr: Expr) -> Result<Expr, ()> {
+        while self.match_tokens(&[TokenType::Comma]) {
+            let comma = self.previous();
+            let right = self.assignment()?;
+            expr = Expr::Binary(Box::new(expr), comma.clone(), Box::new(right));
         }
-
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.equality()?;
-
-        while self.match_tokens(&[TokenType::And]) {
-            let operator = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    fn assignment(&mut self) -> Result<Expr, ()> {
+        let expr = self.binary(1)?;
+
+        if self.match_tokens(&[TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+            match expr {
+                Expr::Get(object, name) => return Ok(Expr::Set(object, name, Box::new(value))),
+                Expr::Index(object, bracket, index) => {
+                    return Ok(Expr::SetIndex(object, bracket, index, Box::new(value)))
+                }
+                Expr::Variable(name, _) if name.token_type == TokenType::Identifier => {
+                    return Ok(Expr::Assign(name, Box::new(value), None))
+                }
+                _ => {}
+            }
+            self.diagnostics.error(equals, "Invalid assignment target.");
+            return Err(());
         }
 
-        Ok(expr)
-    }
-
-    fn equality(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.comparison()?;
-
-        while self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+        if self.match_tokens(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
             let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+            let value = self.assignment()?;
+            if let Expr::Variable(name, _) = expr {
+                if name.token_type == TokenType::Identifier {
+                    return Ok(Expr::Compound(
+                        name,
+                        operator.clone(),
+                        Box::new(value),
+                        None,
+                    ));
+                }
+            }
+            self.diagnostics
+                .error(operator, "Invalid assignment target.");
+            return Err(());
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.term()?;
+    //? Precedence climbing over every binary level from `or` down to `*`/`/`.
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, ()> {
+        let mut expr = self.unary()?;
 
-        while self.match_tokens(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+        while let Some(precedence) = binary_precedence(&self.peek().token_type) {
+            if precedence < min_precedence {
+                break;
+            }
+            let operator = self.advance();
+            //? `or` has always grouped to the right, as does `**`; every other operator groups
+            //? to the left.
+            let next = if matches!(operator.token_type, TokenType::Or | TokenType::StarStar) {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let right = self.binary(next)?;
+            expr = Expr::Binary(Box::new(expr), operator.clone(), Box::new(right));
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.factor()?;
-
-        while self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
+    fn unary(&mut self) -> Result<Expr, ()> {
+        if self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+            let right = self.unary()?;
+            return Ok(Expr::Unary(operator.clone(), Box::new(right)));
         }
 
-        Ok(expr)
+        self.call()
     }
 
-    fn factor(&mut self) -> Result<Expr, ()> {
-        let mut expr = self.unary()?;
-
-        while self.match_tokens(&[TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    fn call(&mut self) -> Result<Expr, ()> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_tokens(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_tokens(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::Get(Box::new(expr), name.clone());
+            } else if self.match_tokens(&[TokenType::LeftBracket]) {
+                let bracket = self.previous();
+                let index = self.express()?;
+                self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index(Box::new(expr), bracket.clone(), Box::new(index));
+            } else {
+                break;
+            }
         }
 
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, ()> {
-        if self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            return Ok(Expr::Unary(operator, Box::new(right)));
+    //? Argument, parameter, list and map item lists all allow one trailing comma.
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ()> {
+        let mut arguments = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                arguments.push(self.assignment()?);
+                if !self.match_tokens(&[TokenType::Comma]) || self.check(&TokenType::RightParen) {
+                    break;
+                }
+            }
         }
 
-        self.primary()
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        Ok(Expr::Call(Box::new(callee), arguments))
     }
 
     fn primary(&mut self) -> Result<Expr, ()> {
+        if self.match_tokens(&[TokenType::LeftBracket]) {
+            let mut elements = vec![];
+            if !self.check(&TokenType::RightBracket) {
+                loop {
+                    elements.push(self.assignment()?);
+                    if !self.match_tokens(&[TokenType::Comma])
+                        || self.check(&TokenType::RightBracket)
+                    {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+            return Ok(Expr::List(elements));
+        }
+
         if self.match_tokens(&[TokenType::False, TokenType::True, TokenType::Nil]) {
-            return Ok(Expr::Literal(self.previous()));
+            return Ok(Expr::Literal(self.previous().clone()));
         }
 
         if let TokenType::Number(_) = self.peek().token_type {
             self.advance();
-            return Ok(Expr::Literal(self.previous()));
+            return Ok(Expr::Literal(self.previous().clone()));
         }
 
         if let TokenType::String(_) = self.peek().token_type {
@@ -352,44 +705,74 @@ impl<'a> Parser<'a> {
                     token_type: TokenType::String(s.to_string()),
                     lexeme: s.to_string(),
                     line: self.previous().line,
+                    column: self.previous().column,
                 }));
             }
         }
 
+        if self.match_tokens(&[TokenType::This]) {
+            let token = self.previous();
+            if self.classes.is_empty() {
+                self.diagnostics
+                    .error(token, "Can't use 'this' outside of a class.");
+                return Err(());
+            }
+            return Ok(Expr::Variable(token.clone(), None));
+        }
+
+        if self.match_tokens(&[TokenType::Super]) {
+            let keyword = self.previous();
+            match self.classes.last() {
+                None => {
+                    self.diagnostics
+                        .error(keyword, "Can't use 'super' outside of a class.");
+                    return Err(());
+                }
+                Some(ClassKind::Class) => {
+                    self.diagnostics
+                        .error(keyword, "Can't use 'super' in a class with no superclass.");
+                    return Err(());
+                }
+                Some(ClassKind::Subclass) => {}
+            }
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            return Ok(Expr::Super(method.clone(), None));
+        }
+
         if self.match_tokens(&[TokenType::LeftParen]) {
-            let stmt = self.parse_statement()?;
+            let expr = self.express()?;
             self.consume(TokenType::RightParen, "Unmatched parentheses.")?;
-            return Ok(Expr::Group(Box::new(stmt)));
+            return Ok(Expr::Group(Box::new(expr)));
         }
 
+        //? `{ key: value, ... }` is a map; a single braced expression still groups.
         if self.match_tokens(&[TokenType::LeftBrace]) {
-            let stmt = self.parse_statement()?;
-            self.consume(TokenType::RightBrace, "Unmatched brace.")?;
-            return Ok(Expr::Group(Box::new(stmt)));
+            let brace = self.previous();
+            if self.match_tokens(&[TokenType::RightBrace]) {
+                return Ok(Expr::Map(brace.clone(), vec![]));
+            }
+            let expr = self.assignment()?;
+            if !self.match_tokens(&[TokenType::Colon]) {
+                self.consume(TokenType::RightBrace, "Unmatched brace.")?;
+                return Ok(Expr::Group(Box::new(expr)));
+            }
+            let mut entries = vec![(expr, self.assignment()?)];
+            while self.match_tokens(&[TokenType::Comma]) && !self.check(&TokenType::RightBrace) {
+                let key = self.assignment()?;
+                self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                entries.push((key, self.assignment()?));
+            }
+            self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+            return Ok(Expr::Map(brace.clone(), entries));
         }
 
-        if self.match_tokens(&[
-            TokenType::And,
-            TokenType::Class,
-            TokenType::Else,
-            TokenType::For,
-            TokenType::Fun,
-            TokenType::If,
-            TokenType::Or,
-            TokenType::Print,
-            TokenType::Return,
-            TokenType::Super,
-            TokenType::This,
-            TokenType::Var,
-            TokenType::While,
-            TokenType::Identifier,
-        ]) {
-            return Ok(Expr::Literal(self.previous()));
+        if self.match_tokens(&[TokenType::Identifier]) {
+            return Ok(Expr::Variable(self.previous().clone(), None));
         }
 
         let token = self.advance();
-        self.reporter
-            .error(token.line, &token.lexeme, "Expect expression.");
+        self.error(token, "Expect expression.");
         Err(())
     }
 
@@ -411,36 +794,234 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ()> {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&'a Token, ()> {
         if self.check(&token_type) {
             Ok(self.advance())
         } else {
-            let token = self.advance();
-            self.reporter.error(token.line, &token.lexeme, message);
+            //? Report the offending token itself: at the end of input `advance` would return
+            //? the last real token, which may sit on an earlier line.
+            let token = &self.tokens[self.current];
+            self.advance();
+            self.error(token, message);
             Err(())
         }
     }
 
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    //? Borrows from the token slice rather than `self`; clone only when storing into the AST.
+    //? The scanner has already reported an `Error` token, so it's not reported again.
+    fn error(&mut self, token: &Token, message: &str) {
+        if token.token_type != TokenType::Error {
+            self.diagnostics.error(token, message);
+        }
+    }
+
+    fn previous(&self) -> &'a Token {
+        &self.tokens[self.current - 1]
     }
 }
 
-pub struct ErrorReporter {
-    had_error: bool,
+fn binary_precedence(token_type: &TokenType) -> Option<u8> {
+    match token_type {
+        TokenType::Or => Some(1),
+        TokenType::And => Some(2),
+        TokenType::BangEqual | TokenType::EqualEqual => Some(3),
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+            Some(4)
+        }
+        TokenType::Pipe => Some(5),
+        TokenType::Caret => Some(6),
+        TokenType::Ampersand => Some(7),
+        TokenType::LessLess | TokenType::GreaterGreater => Some(8),
+        TokenType::Minus | TokenType::Plus => Some(9),
+        TokenType::Slash | TokenType::Star | TokenType::Percent => Some(10),
+        TokenType::StarStar => Some(11),
+        _ => None,
+    }
 }
 
-impl ErrorReporter {
-    pub fn new() -> Self {
-        Self { had_error: false }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::Env;
+    use crate::scanner::Scanner;
+    use crate::token::Value;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        assert!(parser.diagnostics().is_empty());
+        parser.statements().to_vec()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        parser.diagnostics().iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn else_if_chain_nests_in_the_else_branch() {
+        let stmts = parse("if (a) print 1; else if (b) print 2; else print 3;");
+        let [Stmt::If(_, _, Some(else_branch))] = stmts.as_slice() else {
+            panic!("expected one if statement: {:?}", stmts);
+        };
+        let Stmt::If(condition, if_branch, Some(last)) = else_branch.as_ref() else {
+            panic!("expected else if: {:?}", else_branch);
+        };
+        assert_eq!(condition.to_string(), "b");
+        assert_eq!(if_branch.to_string(), "print 2.0");
+        assert_eq!(last.to_string(), "print 3.0");
+    }
+
+    #[test]
+    fn dangling_else_binds_to_the_nearest_if() {
+        let stmts = parse("if (a) if (b) print 1; else print 2;");
+        let [Stmt::If(_, inner, None)] = stmts.as_slice() else {
+            panic!("expected an if without else: {:?}", stmts);
+        };
+        let Stmt::If(_, _, Some(else_branch)) = inner.as_ref() else {
+            panic!("expected the inner if to own the else: {:?}", inner);
+        };
+        assert_eq!(else_branch.to_string(), "print 2.0");
+    }
+
+    #[test]
+    fn else_if_chain_displays_flat() {
+        let stmts = parse("if (a) { print 1; } else if (b) { print 2; } else { print 3; }");
+        assert_eq!(
+            stmts[0].to_string(),
+            "if a {\n  print 1.0\n} else if b {\n  print 2.0\n} else {\n  print 3.0\n}"
+        );
+        let stmts = parse("if (a) print 1; else if (b) print 2; else print 3;");
+        assert_eq!(
+            stmts[0].to_string(),
+            "if a print 1.0 else if b print 2.0 else print 3.0"
+        );
+    }
+
+    #[test]
+    fn empty_blocks_parse_and_evaluate_to_nil() {
+        let stmts = parse("{}\nif (a) {} else {}\nvar m = {};");
+        let [Stmt::Block(body), Stmt::If(_, if_branch, Some(else_branch)), declaration] =
+            stmts.as_slice()
+        else {
+            panic!("expected a block, an if and a var: {:?}", stmts);
+        };
+        assert!(body.is_empty());
+        assert!(matches!(if_branch.as_ref(), Stmt::Block(body) if body.is_empty()));
+        assert!(matches!(else_branch.as_ref(), Stmt::Block(body) if body.is_empty()));
+        //? `{}` in expression position is still an empty map.
+        assert_eq!(declaration.to_string(), "var m = (map)");
+        assert!(matches!(stmts[0].evaluate(Env::new()), Ok(Value::Nil)));
+    }
+
+    #[test]
+    fn property_chains_nest_left_to_right() {
+        let stmts = parse("a.b.c;\na.b.c = d.e;");
+        let [Stmt::Expr(get), Stmt::Expr(set)] = stmts.as_slice() else {
+            panic!("expected two expression statements: {:?}", stmts);
+        };
+        let Expr::Get(object, c) = get else {
+            panic!("expected a get: {:?}", get);
+        };
+        assert_eq!(c.lexeme, "c");
+        assert!(matches!(object.as_ref(), Expr::Get(a, b)
+            if b.lexeme == "b" && matches!(a.as_ref(), Expr::Variable(a, _) if a.lexeme == "a")));
+        let Expr::Set(object, c, value) = set else {
+            panic!("expected a set: {:?}", set);
+        };
+        assert_eq!(c.lexeme, "c");
+        assert!(matches!(object.as_ref(), Expr::Get(_, b) if b.lexeme == "b"));
+        assert!(matches!(value.as_ref(), Expr::Get(_, e) if e.lexeme == "e"));
+        assert_eq!(get.to_string(), "(get (get a b) c)");
+        assert_eq!(set.to_string(), "(set (get a b) c (get d e))");
+    }
+
+    #[test]
+    fn keywords_are_not_expressions() {
+        assert_eq!(
+            errors("print var x = 1;"),
+            ["[line 1] Error at 'var': Expect expression."]
+        );
+        assert_eq!(
+            errors("print !fun f() {}"),
+            ["[line 1] Error at 'fun': Expect expression."]
+        );
+        assert_eq!(
+            errors("print 1 + class A {}"),
+            ["[line 1] Error at 'class': Expect expression."]
+        );
+    }
+
+    #[test]
+    fn error_tokens_skip_their_statement_without_another_diagnostic() {
+        let source =
+            "print @;\n{ print 1 + @ + 2; print 3; }\nvar @ = 1;\nif (@) print 4;\nprint 5;";
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        assert_eq!(scanner.diagnostics().len(), 4);
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        assert!(parser.diagnostics().is_empty());
+        let printed: Vec<String> = parser.statements().iter().map(Stmt::to_string).collect();
+        //? Recovery resumes at the next statement keyword, here the `print` inside the `if`.
+        assert_eq!(printed, ["{\n  print 3.0\n}", "print 4.0", "print 5.0"]);
+    }
+
+    #[test]
+    fn parse_expression_evaluates_a_lone_expression() {
+        let mut scanner = Scanner::new("1 + 2 * (3 - 1)");
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        let expr = parser.parse_expression().unwrap();
+        assert_eq!(expr.to_string(), "(+ 1.0 (* 2.0 (group (- 3.0 1.0))))");
+        assert_eq!(expr.evaluate(Env::new()).unwrap(), Value::Number(5.0));
+    }
+
+    #[test]
+    fn parse_expression_rejects_trailing_tokens() {
+        let mut scanner = Scanner::new("1 + 2;");
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        assert!(parser.parse_expression().is_err());
+        assert_eq!(
+            parser.diagnostics()[0].to_string(),
+            "[line 1] Error at ';': Expect end of expression."
+        );
     }
 
-    pub fn error(&mut self, line: usize, token: &str, message: &str) {
-        self.report(line, token, message);
-        self.had_error = true;
+    #[test]
+    fn identical_programs_parse_equal_whatever_their_layout() {
+        let compact = parse("fun f(a) { if (a > 1) return a * 2; }\nprint f(3);");
+        let spread = parse("\n\nfun f(a) {\n  if (a > 1)\n    return a * 2;\n}\n\n  print f(3);");
+        assert_eq!(compact, spread);
+        assert_ne!(
+            compact,
+            parse("fun f(a) { if (a > 1) return a * 3; }\nprint f(3);")
+        );
     }
 
-    fn report(&self, line: usize, token: &str, message: &str) {
-        eprintln!("[line {}] Error at '{}': {}", line, token, message);
+    #[test]
+    fn parsing_does_not_clone_every_token() {
+        let source = (0..200)
+            .map(|i| format!("fun f{i}(a, b) {{\n  if (a < b) return a * {i} + b;\n  return \"v\" + a;\n}}\n"))
+            .collect::<String>();
+        let mut scanner = Scanner::new(&source);
+        scanner.tokenize();
+        let (statements, allocations) = crate::allocations::count(|| {
+            let mut parser = Parser::new(scanner.tokens());
+            parser.parse();
+            parser.statements().len()
+        });
+        assert_eq!(statements, 200);
+        //? Nodes still own the tokens they keep; cloning on every step cost over two per token.
+        assert!(
+            allocations < scanner.tokens().len() * 3 / 2,
+            "{allocations}"
+        );
     }
 }
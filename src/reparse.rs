@@ -0,0 +1,311 @@
+use crate::diagnostics;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Expr, Stmt, TokenType};
+
+const INDENT_WIDTH: usize = 2;
+
+//? Renders `stmt` back into Lox source that parses to the same tree. Parentheses come
+//? only from `Expr::Group`, as the tree already encodes precedence.
+pub fn reparse(stmt: &Stmt) -> String {
+    statement(stmt, 0)
+}
+
+//? Whether scanning and parsing `reparse(stmt)` gives back exactly `stmt`.
+pub fn round_trips(stmt: &Stmt) -> bool {
+    let source = reparse(stmt);
+    let mut scanner = Scanner::new(&source);
+    scanner.tokenize();
+    let mut parser = Parser::new(scanner.tokens());
+    parser.parse();
+    !diagnostics::has_errors(scanner.diagnostics())
+        && !diagnostics::has_errors(parser.diagnostics())
+        && parser.statements() == std::slice::from_ref(stmt)
+}
+
+fn indent(depth: usize) -> String {
+    " ".repeat(INDENT_WIDTH * depth)
+}
+
+fn statement(stmt: &Stmt, depth: usize) -> String {
+    match stmt {
+        Stmt::Print(_)
+        | Stmt::Declare(..)
+        | Stmt::Assign(..)
+        | Stmt::Return(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Expr(_) => format!("{};", clause(stmt, depth)),
+        _ => clause(stmt, depth),
+    }
+}
+
+//? A statement without its closing `;`, as written inside `if (...)` and `for (...)`.
+fn clause(stmt: &Stmt, depth: usize) -> String {
+    match stmt {
+        Stmt::Block(stmts) => body(stmts, depth),
+        Stmt::Print(exprs) => format!("print {}", list(exprs)),
+        Stmt::While(condition, body) => format!(
+            "while ({}) {}",
+            clause(condition, depth),
+            statement(body, depth)
+        ),
+        Stmt::DoWhile(body, condition) => format!(
+            "do {} while ({})",
+            statement(body, depth),
+            clause(condition, depth)
+        ),
+        Stmt::Repeat(count, body) => format!(
+            "repeat ({}) {}",
+            clause(count, depth),
+            statement(body, depth)
+        ),
+        Stmt::For(init, condition, increment, body) => {
+            let clauses = [init, condition, increment]
+                .map(|clause_stmt| {
+                    clause_stmt
+                        .as_ref()
+                        .map_or(String::new(), |stmt| clause(stmt, depth))
+                })
+                .join("; ");
+            format!("for ({}) {}", clauses.trim_end(), statement(body, depth))
+        }
+        Stmt::If(condition, if_branch, else_branch) => {
+            let mut text = format!(
+                "if ({}) {}",
+                clause(condition, depth),
+                statement(if_branch, depth)
+            );
+            if let Some(else_branch) = else_branch {
+                text.push_str(" else ");
+                text.push_str(&statement(else_branch, depth));
+            }
+            text
+        }
+        Stmt::Switch(value, cases, default) => {
+            let mut text = format!("switch ({}) {{\n", expression(value));
+            for (case, stmts) in cases {
+                text.push_str(&format!(
+                    "{}case {}:\n",
+                    indent(depth + 1),
+                    expression(case)
+                ));
+                text.push_str(&arm(stmts, depth + 2));
+            }
+            if let Some(stmts) = default {
+                text.push_str(&format!("{}default:\n", indent(depth + 1)));
+                text.push_str(&arm(stmts, depth + 2));
+            }
+            text.push_str(&indent(depth));
+            text.push('}');
+            text
+        }
+        Stmt::Declare(name, value, constant) => {
+            let keyword = if *constant { "const" } else { "var" };
+            match value {
+                Some(value) => format!("{} {} = {}", keyword, name.lexeme, clause(value, depth)),
+                None => format!("{} {}", keyword, name.lexeme),
+            }
+        }
+        Stmt::Assign(name, value, _) => format!("{} = {}", name, clause(value, depth)),
+        Stmt::Function(name, params, rest, stmts) => {
+            format!("fun {}", function(name, params, rest, stmts, depth))
+        }
+        Stmt::Class(name, superclass, methods) => {
+            let mut text = format!("class {}", name);
+            if let Some(superclass) = superclass {
+                text.push_str(&format!(" < {}", expression(superclass)));
+            }
+            if methods.is_empty() {
+                return text + " {}";
+            }
+            text.push_str(" {\n");
+            for method in methods {
+                if let Stmt::Function(name, params, rest, stmts) = method {
+                    text.push_str(&indent(depth + 1));
+                    text.push_str(&function(name, params, rest, stmts, depth + 1));
+                    text.push('\n');
+                }
+            }
+            text.push_str(&indent(depth));
+            text.push('}');
+            text
+        }
+        Stmt::Return(Some(value)) => format!("return {}", expression(value)),
+        Stmt::Return(None) => "return".to_string(),
+        Stmt::Break => "break".to_string(),
+        Stmt::Continue => "continue".to_string(),
+        Stmt::Expr(expr) => expression(expr),
+    }
+}
+
+fn body(stmts: &[Stmt], depth: usize) -> String {
+    if stmts.is_empty() {
+        return "{}".to_string();
+    }
+    format!("{{\n{}{}}}", arm(stmts, depth + 1), indent(depth))
+}
+
+//? One statement per line at `depth`, each ending in a newline.
+fn arm(stmts: &[Stmt], depth: usize) -> String {
+    stmts
+        .iter()
+        .map(|stmt| format!("{}{}\n", indent(depth), statement(stmt, depth)))
+        .collect()
+}
+
+fn function(
+    name: &str,
+    params: &[String],
+    rest: &Option<String>,
+    stmts: &[Stmt],
+    depth: usize,
+) -> String {
+    let params = params
+        .iter()
+        .cloned()
+        .chain(rest.iter().map(|rest| format!("...{}", rest)))
+        .collect::<Vec<_>>();
+    format!("{}({}) {}", name, params.join(", "), body(stmts, depth))
+}
+
+fn expression(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(left, operator, right) if operator.token_type == TokenType::Comma => {
+            format!("{}, {}", expression(left), expression(right))
+        }
+        Expr::Binary(left, operator, right) => format!(
+            "{} {} {}",
+            expression(left),
+            operator.lexeme,
+            expression(right)
+        ),
+        //? String literals keep only their decoded contents as the lexeme, so quotes and
+        //? anything that would read as an escape are escaped again.
+        Expr::Literal(token) => match &token.token_type {
+            TokenType::String(s) => format!(
+                "\"{}\"",
+                s.replace("\\u{", "\\u{5C}u{").replace('"', "\\u{22}")
+            ),
+            _ => token.lexeme.clone(),
+        },
+        Expr::Variable(token, _) => token.lexeme.clone(),
+        Expr::Unary(operator, right) => format!("{}{}", operator.lexeme, expression(right)),
+        Expr::Group(expr) => format!("({})", expression(expr)),
+        Expr::Call(callee, arguments) => {
+            format!("{}({})", expression(callee), list(arguments))
+        }
+        Expr::Get(object, name) => format!("{}.{}", expression(object), name.lexeme),
+        Expr::Set(object, name, value) => format!(
+            "{}.{} = {}",
+            expression(object),
+            name.lexeme,
+            expression(value)
+        ),
+        Expr::List(elements) => format!("[{}]", list(elements)),
+        Expr::Map(_, entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", expression(key), expression(value)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expr::Index(object, _, index) => {
+            format!("{}[{}]", expression(object), expression(index))
+        }
+        Expr::SetIndex(object, _, index, value) => format!(
+            "{}[{}] = {}",
+            expression(object),
+            expression(index),
+            expression(value)
+        ),
+        Expr::Assign(name, value, _) => format!("{} = {}", name.lexeme, expression(value)),
+        Expr::Compound(name, operator, value, _) => {
+            format!("{} {} {}", name.lexeme, operator.lexeme, expression(value))
+        }
+        Expr::Super(method, _) => format!("super.{}", method.lexeme),
+    }
+}
+
+fn list(exprs: &[Expr]) -> String {
+    exprs.iter().map(expression).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //? Parses `source` and checks that every top-level statement round-trips, showing the
+    //? rendered text when one doesn't.
+    fn assert_round_trips(source: &str) {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        assert!(scanner.diagnostics().is_empty() && parser.diagnostics().is_empty());
+        for stmt in parser.statements() {
+            assert!(
+                round_trips(stmt),
+                "does not round-trip:\n{}\nrendered as:\n{}",
+                stmt,
+                reparse(stmt)
+            );
+        }
+    }
+
+    #[test]
+    fn expressions_keep_their_grouping() {
+        assert_round_trips("print (1 + 2) * 3, 1 + 2 * 3, -(-x), !!y, 2 ** 3 ** 2, 7 % 3;");
+        assert_round_trips("a = b = c; x += 1; a.b.c = d[0][1]; f(1)(2);");
+        assert_round_trips("print a and b or c, a & b | c ^ d << 1, [1, [2]], {\"k\": {}};");
+    }
+
+    #[test]
+    fn strings_keep_their_escapes() {
+        assert_round_trips(
+            "print \"tab\\there\", \"quote \\u{22}\", \"line\nbreak\", \"\\\\u{41}\";",
+        );
+    }
+
+    #[test]
+    fn nested_blocks_and_control_flow() {
+        assert_round_trips(
+            "{ var a = 1; { var b = a; { print b; } } }
+            while (a < 3) { a = a + 1; }
+            do { a = a - 1; } while (a > 0);
+            repeat (2) print a;
+            for (var i = 0; i < 3; i = i + 1) { if (i == 1) continue; else print i; }
+            for (;;) { break; }
+            if (a) if (b) print 1; else print 2;
+            switch (a) { case 1: print 1; case 2: { print 2; } default: print 0; }",
+        );
+    }
+
+    #[test]
+    fn loop_exits_parse_as_statements() {
+        let mut scanner = Scanner::new("while (a) { continue; break; }");
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        assert!(parser.diagnostics().is_empty());
+        let [Stmt::While(_, body)] = parser.statements() else {
+            panic!("expected a single while loop");
+        };
+        assert!(matches!(
+            body.as_ref(),
+            Stmt::Block(stmts) if matches!(stmts.as_slice(), [Stmt::Continue, Stmt::Break])
+        ));
+        assert_round_trips("while (a) { continue; break; }");
+    }
+
+    #[test]
+    fn functions_and_classes() {
+        assert_round_trips(
+            "fun add(a, b, ...rest) { return a + b + len(rest); }
+            fun noop() { return; }
+            const limit = 10;
+            class A { init(x) { this.x = x; } get() { return this.x; } }
+            class B < A { get() { return super.get() + 1; } }",
+        );
+    }
+}
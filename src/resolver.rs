@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::config;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::token::{Expr, Stmt, Token};
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Local>>,
+    //? Names that resolve to no scope must be one of these globals.
+    known_globals: HashSet<String>,
+    diagnostics: Diagnostics,
+}
+
+struct Local {
+    //? Whether its initializer has finished resolving.
+    defined: bool,
+    read: bool,
+    //? Set for `var`/`const` locals, the only ones reported when unused.
+    declaration: Option<Token>,
+}
+
+impl Resolver {
+    //? Reports names that are neither local nor global. Globals are late-bound, so any name
+    //? declared at the top level counts as defined everywhere, even before its declaration runs.
+    pub fn with_known_globals(globals: Vec<String>) -> Self {
+        Self {
+            scopes: vec![],
+            known_globals: globals.into_iter().collect(),
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.as_slice()
+    }
+
+    pub fn resolve(&mut self, stmts: &mut [Stmt]) {
+        for stmt in stmts.iter() {
+            Self::collect_globals(stmt, &mut self.known_globals);
+        }
+
+        for stmt in stmts {
+            self.resolve_statement(stmt);
+        }
+    }
+
+    //? Declarations that run in the global environment: top-level ones, plus those under
+    //? control flow that doesn't open a scope, like `if (c) var x = 1;`. Blocks and switch
+    //? arms only share the global scope when they declare nothing themselves.
+    fn collect_globals(stmt: &Stmt, globals: &mut HashSet<String>) {
+        match stmt {
+            Stmt::Block(stmts) if !Stmt::declares_names(stmts) => {
+                for stmt in stmts {
+                    Self::collect_globals(stmt, globals);
+                }
+            }
+            Stmt::Switch(_, cases, default) => {
+                for stmts in cases.iter().map(|(_, stmts)| stmts).chain(default) {
+                    if !Stmt::declares_names(stmts) {
+                        for stmt in stmts {
+                            Self::collect_globals(stmt, globals);
+                        }
+                    }
+                }
+            }
+            Stmt::Declare(name, _, _) => {
+                globals.insert(name.lexeme.clone());
+            }
+            Stmt::Function(name, _, _, _) | Stmt::Class(name, _, _) => {
+                globals.insert(name.clone());
+            }
+            Stmt::For(init, condition, increment, body) => {
+                for stmt in [init, condition, increment]
+                    .into_iter()
+                    .flatten()
+                    .chain([body])
+                {
+                    Self::collect_globals(stmt, globals);
+                }
+            }
+            Stmt::While(first, second)
+            | Stmt::DoWhile(first, second)
+            | Stmt::Repeat(first, second) => {
+                Self::collect_globals(first, globals);
+                Self::collect_globals(second, globals);
+            }
+            Stmt::Assign(_, value, _) => Self::collect_globals(value, globals),
+            Stmt::If(condition, if_branch, else_branch) => {
+                for stmt in [condition, if_branch].into_iter().chain(else_branch) {
+                    Self::collect_globals(stmt, globals);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Block(stmts) => self.resolve_block(stmts),
+            Stmt::Print(exprs) => {
+                for expr in exprs {
+                    self.resolve_expression(expr);
+                }
+            }
+            Stmt::While(condition, body) | Stmt::Repeat(condition, body) => {
+                self.resolve_statement(condition);
+                self.resolve_statement(body);
+            }
+            Stmt::DoWhile(body, condition) => {
+                self.resolve_statement(body);
+                self.resolve_statement(condition);
+            }
+            Stmt::For(init, condition, increment, body) => {
+                //? `for` runs its clauses in the enclosing environment, so no new scope here.
+                for clause in [init, condition, increment].into_iter().flatten() {
+                    self.resolve_statement(clause);
+                }
+                self.resolve_statement(body);
+            }
+            Stmt::If(condition, if_branch, else_branch) => {
+                self.resolve_statement(condition);
+                self.resolve_statement(if_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Stmt::Switch(value, cases, default) => {
+                self.resolve_expression(value);
+                for (case, body) in cases {
+                    self.resolve_expression(case);
+                    self.resolve_block(body);
+                }
+                if let Some(default) = default {
+                    self.resolve_block(default);
+                }
+            }
+            Stmt::Declare(var, stmt, _) => {
+                self.declare(&var.lexeme);
+                if let Some(stmt) = stmt {
+                    self.resolve_statement(stmt);
+                }
+                self.define(&var.lexeme);
+                if let Some(local) = self
+                    .scopes
+                    .last_mut()
+                    .and_then(|scope| scope.get_mut(&var.lexeme))
+                {
+                    local.declaration = Some(var.clone());
+                }
+            }
+            Stmt::Assign(var, stmt, depth) => {
+                self.resolve_statement(stmt);
+                *depth = self.resolve_local(var);
+            }
+            Stmt::Function(name, params, rest, body) => {
+                self.declare(name);
+                self.define(name);
+                //? Bodies are only shared once functions are created at runtime, so nothing
+                //? is copied.
+                self.resolve_function(params, rest, Arc::make_mut(body).as_mut_slice());
+            }
+            Stmt::Class(name, superclass, methods) => {
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    self.resolve_expression(superclass);
+                    self.begin_scope();
+                    self.define("super");
+                }
+
+                self.begin_scope();
+                self.define("this");
+                for method in methods.iter_mut() {
+                    if let Stmt::Function(_, params, rest, body) = method {
+                        self.resolve_function(params, rest, Arc::make_mut(body).as_mut_slice());
+                    }
+                }
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+            }
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::Break | Stmt::Continue => {}
+            Stmt::Expr(expr) => self.resolve_expression(expr),
+        }
+    }
+
+    fn resolve_block(&mut self, stmts: &mut [Stmt]) {
+        let scoped = Stmt::declares_names(stmts);
+        if scoped {
+            self.begin_scope();
+        }
+        for stmt in stmts {
+            self.resolve_statement(stmt);
+        }
+        if scoped {
+            self.end_scope();
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[String], rest: &Option<String>, body: &mut [Stmt]) {
+        self.begin_scope();
+        for param in params.iter().chain(rest) {
+            self.define(param);
+        }
+        for stmt in body {
+            self.resolve_statement(stmt);
+        }
+        self.end_scope();
+    }
+
+    fn resolve_expression(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Binary(left, _, right) => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Literal(_) => {}
+            Expr::Variable(name, depth) => {
+                if let Some(false) = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&name.lexeme))
+                    .map(|local| local.defined)
+                {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                *depth = self.resolve_local(&name.lexeme);
+                if let Some(distance) = *depth {
+                    let index = self.scopes.len() - 1 - distance;
+                    if let Some(local) = self.scopes[index].get_mut(&name.lexeme) {
+                        local.read = true;
+                    }
+                }
+                if depth.is_none() && !self.known_globals.contains(&name.lexeme) {
+                    let message = format!("Undefined variable '{}'.", name.lexeme);
+                    self.error(name, &message);
+                }
+            }
+            Expr::Unary(_, expr) => self.resolve_expression(expr),
+            Expr::Group(expr) => self.resolve_expression(expr),
+            Expr::Call(callee, arguments) => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expr::Get(object, _) => self.resolve_expression(object),
+            Expr::List(elements) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Map(_, entries) => {
+                for (key, value) in entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            Expr::Index(object, _, index) => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::SetIndex(object, _, index, value) => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+                self.resolve_expression(value);
+            }
+            Expr::Set(object, _, value) => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expr::Assign(name, value, depth) | Expr::Compound(name, _, value, depth) => {
+                self.resolve_expression(value);
+                *depth = self.resolve_local(&name.lexeme);
+            }
+            Expr::Super(_, depth) => *depth = self.resolve_local("super"),
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    //? Under `--warn-unused`, reports the scope's unread `var`/`const` locals in source order.
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        if !config::warn_unused() {
+            return;
+        }
+        let mut unused = scope
+            .into_values()
+            .filter(|local| !local.read)
+            .filter_map(|local| local.declaration)
+            .collect::<Vec<_>>();
+        unused.sort_by_key(|token| (token.line, token.column));
+        for token in unused {
+            let message = format!("Unused variable '{}'.", token.lexeme);
+            self.diagnostics.warning(&token, &message);
+        }
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(
+                name.to_string(),
+                Local {
+                    defined: false,
+                    read: false,
+                    declaration: None,
+                },
+            );
+        }
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            match scope.get_mut(name) {
+                Some(local) => local.defined = true,
+                None => {
+                    scope.insert(
+                        name.to_string(),
+                        Local {
+                            defined: true,
+                            read: false,
+                            declaration: None,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.diagnostics.error(token, message);
+    }
+}
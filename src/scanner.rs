@@ -1,7 +1,9 @@
+use std::collections::VecDeque;
 use std::iter::Peekable;
-use std::process::ExitCode;
 use std::str::Chars;
 
+use crate::config;
+use crate::diagnostics::{Diagnostic, Diagnostics};
 use crate::token::{Token, TokenType};
 
 pub struct Scanner<'a> {
@@ -11,7 +13,9 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
-    error: bool,
+    line_start: usize,
+    column: usize,
+    diagnostics: Diagnostics,
 }
 
 impl<'a> Scanner<'a> {
@@ -23,10 +27,25 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
-            error: false,
+            line_start: 0,
+            column: 1,
+            diagnostics: Diagnostics::default(),
         }
     }
 
+    //? Starts over on `source`, keeping the token buffer's allocation.
+    pub fn reset(&mut self, source: &'a str) {
+        self.source = source;
+        self.chars = source.chars().peekable();
+        self.tokens.clear();
+        self.start = 0;
+        self.current = 0;
+        self.line = 1;
+        self.line_start = 0;
+        self.column = 1;
+        self.diagnostics.clear();
+    }
+
     fn advance(&mut self) -> Option<char> {
         if let Some(c) = self.chars.next() {
             self.current += c.len_utf8();
@@ -49,158 +68,498 @@ impl<'a> Scanner<'a> {
             token_type,
             lexeme: self.lexeme().to_string(),
             line: self.line,
+            column: self.column,
         })
     }
 
-    fn error(&mut self, line: usize, message: &str) {
-        eprintln!("[line {}] Error: {}", line, message);
-        self.error = true;
+    //? 1-based column of byte offset `at`, counting each character (tabs included) once.
+    fn column_at(&self, at: usize) -> usize {
+        self.source[self.line_start..at].chars().count() + 1
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    //? Decodes the `u{...}` after a backslash at byte offset `at` into the char it names.
+    //? Reports the escape and returns `None` when the braces or code point are invalid; the
+    //? string still becomes a token, keeping the bad escape as written.
+    fn unicode_escape(&mut self, at: usize) -> Option<char> {
+        self.advance();
+        self.advance();
+        let mut code_point = 0u32;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+            self.advance();
+            code_point = code_point.saturating_mul(16).saturating_add(digit);
+            digits += 1;
+        }
+
+        let column = self.column_at(at);
+        if digits == 0 || self.peek() != Some(&'}') {
+            self.error(self.line, column, "Invalid unicode escape.");
+            return None;
+        }
+        self.advance();
+        let decoded = char::from_u32(code_point);
+        if decoded.is_none() {
+            self.error(self.line, column, "Invalid code point in unicode escape.");
+        }
+        decoded
+    }
+
+    //? Digits that overflow `f64` would otherwise silently become infinity.
+    fn add_number(&mut self, value: f64) {
+        if value.is_infinite() {
+            self.add_error(self.line, self.column, "Number literal out of range.");
+        } else {
+            self.add_token(TokenType::Number(value));
+        }
+    }
+
+    fn error(&mut self, line: usize, column: usize, message: &str) {
+        self.diagnostics.error_at(line, column, message);
+    }
+
+    //? Reports an error and leaves an `Error` token for the lexeme in its place.
+    fn add_error(&mut self, line: usize, column: usize, message: &str) {
+        self.error(line, column, message);
+        self.tokens.push(Token {
+            token_type: TokenType::Error,
+            lexeme: self.lexeme().to_string(),
+            line,
+            column,
+        })
     }
 
     pub fn tokens(&self) -> &[Token] {
         &self.tokens
     }
 
-    pub fn tokenize(&mut self) -> Result<(), ExitCode> {
-        while let Some(c) = self.advance() {
-            self.start = self.current - c.len_utf8();
-            match c {
-                '(' => self.add_token(TokenType::LeftParen),
-                ')' => self.add_token(TokenType::RightParen),
-                '{' => self.add_token(TokenType::LeftBrace),
-                '}' => self.add_token(TokenType::RightBrace),
-                ',' => self.add_token(TokenType::Comma),
-                '.' => self.add_token(TokenType::Dot),
-                '-' => self.add_token(TokenType::Minus),
-                '+' => self.add_token(TokenType::Plus),
-                ';' => self.add_token(TokenType::SemiColon),
-                '*' => self.add_token(TokenType::Star),
-                '=' => {
-                    if self.peek() == Some(&'=') {
-                        self.advance();
-                        self.add_token(TokenType::EqualEqual);
-                    } else {
-                        self.add_token(TokenType::Equal);
-                    }
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.as_slice()
+    }
+
+    pub fn tokenize(&mut self) {
+        while self.scan_token() {}
+        self.add_eof();
+    }
+
+    //? Scans lazily, yielding each token or error as it's found; unlike `tokenize`, tokens
+    //? aren't kept. Errors are still collected in `diagnostics`, and stand in for their
+    //? `Error` tokens.
+    pub fn iter_tokens(&mut self) -> Box<dyn Iterator<Item = Result<Token, Diagnostic>> + '_> {
+        let mut finished = false;
+        let mut pending = VecDeque::new();
+        Box::new(std::iter::from_fn(move || loop {
+            if let Some(item) = pending.pop_front() {
+                return Some(item);
+            }
+            if finished {
+                return None;
+            }
+            let errors = self.diagnostics.as_slice().len();
+            if !self.scan_token() {
+                self.add_eof();
+                finished = true;
+            }
+            pending.extend(
+                self.diagnostics.as_slice()[errors..]
+                    .iter()
+                    .cloned()
+                    .map(Err),
+            );
+            pending.extend(
+                self.tokens
+                    .drain(..)
+                    .filter(|token| token.token_type != TokenType::Error)
+                    .map(Ok),
+            );
+        }))
+    }
+
+    //? Scans one lexeme, which may add a token, an error or nothing (whitespace, comments).
+    //? Returns false once the input is exhausted.
+    fn scan_token(&mut self) -> bool {
+        let Some(c) = self.advance() else {
+            return false;
+        };
+        self.start = self.current - c.len_utf8();
+        self.column = self.column_at(self.start);
+        match c {
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => self.add_token(TokenType::LeftBrace),
+            '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ',' => self.add_token(TokenType::Comma),
+            '.' => {
+                if self.source[self.current..].starts_with("..") {
+                    self.advance();
+                    self.advance();
+                    self.add_token(TokenType::DotDotDot);
+                } else {
+                    self.add_token(TokenType::Dot);
                 }
-                '!' => {
-                    if self.peek() == Some(&'=') {
-                        self.advance();
-                        self.add_token(TokenType::BangEqual);
-                    } else {
-                        self.add_token(TokenType::Bang);
-                    }
+            }
+            '-' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::MinusEqual);
+                } else {
+                    self.add_token(TokenType::Minus);
                 }
-                '<' => {
-                    if self.peek() == Some(&'=') {
-                        self.advance();
-                        self.add_token(TokenType::LessEqual);
-                    } else {
-                        self.add_token(TokenType::Less);
-                    }
+            }
+            '+' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::PlusEqual);
+                } else {
+                    self.add_token(TokenType::Plus);
                 }
-                '>' => {
-                    if self.peek() == Some(&'=') {
+            }
+            ';' => self.add_token(TokenType::SemiColon),
+            ':' => self.add_token(TokenType::Colon),
+            '*' => {
+                if self.peek() == Some(&'*') {
+                    self.advance();
+                    self.add_token(TokenType::StarStar);
+                } else if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::StarEqual);
+                } else {
+                    self.add_token(TokenType::Star);
+                }
+            }
+            '%' => self.add_token(TokenType::Percent),
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            '=' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::EqualEqual);
+                } else {
+                    self.add_token(TokenType::Equal);
+                }
+            }
+            '!' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::BangEqual);
+                } else {
+                    self.add_token(TokenType::Bang);
+                }
+            }
+            '<' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::LessEqual);
+                } else if self.peek() == Some(&'<') {
+                    self.advance();
+                    self.add_token(TokenType::LessLess);
+                } else {
+                    self.add_token(TokenType::Less);
+                }
+            }
+            '>' => {
+                if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::GreaterEqual);
+                } else if self.peek() == Some(&'>') {
+                    self.advance();
+                    self.add_token(TokenType::GreaterGreater);
+                } else {
+                    self.add_token(TokenType::Greater);
+                }
+            }
+            '/' => {
+                //? Comment
+                if self.peek() == Some(&'/') {
+                    while self.peek() != Some(&'\n') && self.peek().is_some() {
                         self.advance();
-                        self.add_token(TokenType::GreaterEqual);
-                    } else {
-                        self.add_token(TokenType::Greater);
                     }
+                } else if self.peek() == Some(&'=') {
+                    self.advance();
+                    self.add_token(TokenType::SlashEqual);
+                } else {
+                    self.add_token(TokenType::Slash);
                 }
-                '/' => {
-                    //? Comment
-                    if self.peek() == Some(&'/') {
-                        while self.peek() != Some(&'\n') && self.peek().is_some() {
-                            self.advance();
+            }
+            //? Newlines stay in the value. As in jlox, a multi-line string reports the
+            //? line it ends on; its column is then 1, the start of its text on that line.
+            //? `\u{...}` is the only escape; any other backslash is kept as written.
+            '"' => {
+                let mut value = String::new();
+                while let Some(&c) = self.peek() {
+                    if c == '"' {
+                        break;
+                    }
+                    let at = self.current;
+                    self.advance();
+                    if c == '\n' {
+                        self.new_line();
+                        self.column = 1;
+                    }
+                    if c == '\\'
+                        && self.peek() == Some(&'u')
+                        && self.chars.clone().nth(1) == Some('{')
+                    {
+                        match self.unicode_escape(at) {
+                            Some(c) => value.push(c),
+                            None => value.push_str(&self.source[at..self.current]),
                         }
                     } else {
-                        self.add_token(TokenType::Slash);
+                        value.push(c);
                     }
                 }
-                '"' => {
-                    while self.peek() != Some(&'"') && self.peek().is_some() {
-                        if self.peek() == Some(&'\n') {
-                            self.line += 1;
-                        }
-                        self.advance();
-                    }
 
-                    if self.peek().is_none() {
-                        self.error(self.line, "Unterminated string.");
-                    } else {
-                        self.advance();
-                        self.add_token(TokenType::String(
-                            self.lexeme()[1..self.lexeme().len() - 1].to_string(),
-                        ));
-                    }
+                if self.peek().is_none() {
+                    let column = self.column_at(self.current);
+                    self.add_error(self.line, column, "Unterminated string.");
+                } else {
+                    self.advance();
+                    self.add_token(TokenType::String(value));
+                }
+            }
+            '0' if matches!(self.peek(), Some('x' | 'X')) => {
+                self.advance();
+                let mut value = 0.0;
+                while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                    self.advance();
+                    value = value * 16.0 + digit as f64;
                 }
-                c if c.is_ascii_digit() => {
-                    while self.peek().map_or(false, |c| c.is_ascii_digit()) {
-                        self.advance();
-                    }
 
-                    if self.peek() == Some(&'.')
-                        && self
-                            .chars
-                            .clone()
-                            .nth(1)
-                            .map_or(false, |c| c.is_ascii_digit())
-                    {
+                if self.lexeme().len() == 2 {
+                    let column = self.column_at(self.current);
+                    self.add_error(self.line, column, "Expect hex digits after '0x'.");
+                } else {
+                    self.add_number(value);
+                }
+            }
+            c if c.is_ascii_digit() => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.advance();
+                }
+
+                if self.peek() == Some(&'.')
+                    && self
+                        .chars
+                        .clone()
+                        .nth(1)
+                        .is_some_and(|c| c.is_ascii_digit())
+                {
+                    self.advance();
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
                         self.advance();
-                        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
-                            self.advance();
-                        }
                     }
+                }
 
-                    self.add_token(TokenType::Number(self.lexeme().parse().unwrap()));
+                self.add_number(self.lexeme().parse().unwrap());
+            }
+            c if is_identifier_start(c) => {
+                while self.peek().is_some_and(|c| is_identifier_continue(*c)) {
+                    self.advance();
                 }
-                c if c.is_alphabetic() || c == '_' => {
-                    while self
-                        .peek()
-                        .map_or(false, |c| c.is_alphanumeric() || c == &'_')
-                    {
-                        self.advance();
-                    }
 
-                    let lexeme = self.lexeme();
-                    let token_type = match lexeme {
-                        "and" => TokenType::And,
-                        "class" => TokenType::Class,
-                        "else" => TokenType::Else,
-                        "false" => TokenType::False,
-                        "for" => TokenType::For,
-                        "fun" => TokenType::Fun,
-                        "if" => TokenType::If,
-                        "nil" => TokenType::Nil,
-                        "or" => TokenType::Or,
-                        "print" => TokenType::Print,
-                        "return" => TokenType::Return,
-                        "super" => TokenType::Super,
-                        "this" => TokenType::This,
-                        "true" => TokenType::True,
-                        "var" => TokenType::Var,
-                        "while" => TokenType::While,
-                        _ => TokenType::Identifier,
-                    };
-
-                    self.add_token(token_type);
-                }
-                '\n' => self.line += 1,
-                c if c.is_whitespace() => {}
-                _ => self.error(self.line, &format!("Unexpected character: {c}")),
+                let lexeme = self.lexeme();
+                let token_type = match lexeme {
+                    "and" => TokenType::And,
+                    "break" => TokenType::Break,
+                    "case" => TokenType::Case,
+                    "class" => TokenType::Class,
+                    "const" => TokenType::Const,
+                    "continue" => TokenType::Continue,
+                    "default" => TokenType::Default,
+                    "do" => TokenType::Do,
+                    "else" => TokenType::Else,
+                    "false" => TokenType::False,
+                    "for" => TokenType::For,
+                    "fun" => TokenType::Fun,
+                    "if" => TokenType::If,
+                    "nil" => TokenType::Nil,
+                    "or" => TokenType::Or,
+                    "print" => TokenType::Print,
+                    "repeat" => TokenType::Repeat,
+                    "return" => TokenType::Return,
+                    "super" => TokenType::Super,
+                    "switch" => TokenType::Switch,
+                    "this" => TokenType::This,
+                    "true" => TokenType::True,
+                    "var" => TokenType::Var,
+                    "while" => TokenType::While,
+                    _ => TokenType::Identifier,
+                };
+
+                self.add_token(token_type);
             }
+            c if config::approx_uax31() && is_combining_mark(c) => self.add_error(
+                self.line,
+                self.column,
+                &format!(
+                    "Identifier cannot start with combining mark U+{:04X}.",
+                    c as u32
+                ),
+            ),
+            '\n' => self.new_line(),
+            c if c.is_whitespace() => {}
+            _ => self.add_error(
+                self.line,
+                self.column,
+                &format!("Unexpected character: {c}"),
+            ),
         }
+        true
+    }
 
+    fn add_eof(&mut self) {
         self.tokens.push(Token {
             token_type: TokenType::Eof,
             lexeme: String::new(),
             line: self.line,
+            column: self.column_at(self.current),
         });
+    }
+}
 
-        if self.error {
-            Err(ExitCode::from(65))
-        } else {
-            Ok(())
+//? With `--approx-uax31`, identifiers approximate XID_Start/XID_Continue with what std
+//? offers: the Alphabetic property stands in for letters, and the common combining mark
+//? blocks may continue an identifier (e.g. a decomposed `é`) but never start one. Characters
+//? where the real tables differ, such as some symbols std counts as alphabetic, still pass.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+fn is_identifier_start(c: char) -> bool {
+    if config::approx_uax31() {
+        c == '_' || (c.is_alphabetic() && !is_combining_mark(c))
+    } else {
+        c.is_alphabetic() || c == '_'
+    }
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    if config::approx_uax31() {
+        c == '_' || c.is_alphanumeric() || is_combining_mark(c)
+    } else {
+        c.is_alphanumeric() || c == '_'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "var name = \"lox\";\nfun add(a, b) {\n  return a + b * 0x1F;\n}\nprint add(1.5, 2) >= 3 @ !nil;\n";
+
+    #[test]
+    fn iter_tokens_matches_tokenize() {
+        let mut batch = Scanner::new(SOURCE);
+        batch.tokenize();
+        let expected = batch
+            .tokens()
+            .iter()
+            .filter(|token| token.token_type != TokenType::Error)
+            .map(|token| (token.clone(), token.line, token.column))
+            .collect::<Vec<_>>();
+
+        let mut streaming = Scanner::new(SOURCE);
+        let mut tokens = vec![];
+        let mut errors = vec![];
+        for item in streaming.iter_tokens() {
+            match item {
+                Ok(token) => tokens.push((token.clone(), token.line, token.column)),
+                Err(diagnostic) => errors.push((diagnostic.to_string(), diagnostic.column)),
+            }
         }
+
+        assert_eq!(tokens, expected);
+        assert_eq!(
+            errors,
+            [("[line 5] Error: Unexpected character: @".to_string(), 24)]
+        );
+        assert!(streaming.tokens().is_empty());
+    }
+
+    #[test]
+    fn multi_line_string_keeps_newlines_and_counts_lines() {
+        let mut scanner = Scanner::new("var s = \"one\ntwo\";\n@");
+        scanner.tokenize();
+        let string = &scanner.tokens()[3];
+        assert_eq!(string.token_type, TokenType::String("one\ntwo".to_string()));
+        assert_eq!((string.line, string.column), (2, 1));
+        assert_eq!(scanner.tokens()[4].line, 2);
+        assert_eq!(
+            scanner.diagnostics()[0].to_string(),
+            "[line 3] Error: Unexpected character: @"
+        );
+    }
+
+    fn number_or_error(source: &str) -> Result<f64, String> {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        match (scanner.diagnostics(), &scanner.tokens()[0].token_type) {
+            ([], TokenType::Number(n)) => Ok(*n),
+            ([diagnostic], _) => Err(diagnostic.to_string()),
+            (diagnostics, token_type) => panic!("unexpected {:?} {:?}", diagnostics, token_type),
+        }
+    }
+
+    #[test]
+    fn number_literals_that_overflow_are_errors() {
+        let overflow = Err("[line 1] Error: Number literal out of range.".to_string());
+        assert_eq!(number_or_error(&format!("1{}", "0".repeat(309))), overflow);
+        assert_eq!(
+            number_or_error(&format!("1{}.5", "0".repeat(309))),
+            overflow
+        );
+        assert_eq!(number_or_error(&format!("0x{}", "F".repeat(256))), overflow);
+    }
+
+    #[test]
+    fn large_finite_number_literals_scan() {
+        assert_eq!(number_or_error(&format!("1{}", "0".repeat(308))), Ok(1e308));
+        assert_eq!(
+            number_or_error(&format!("0x{}", "F".repeat(255))),
+            Ok(16f64.powi(255) - 1.0)
+        );
+        assert_eq!(number_or_error(&format!("{:.0}", f64::MAX)), Ok(f64::MAX));
+    }
+
+    #[test]
+    fn reset_scanner_matches_a_fresh_one() {
+        let mut reused = Scanner::new("@\n\"unterminated");
+        reused.tokenize();
+        assert_eq!(reused.diagnostics().len(), 2);
+
+        reused.reset(SOURCE);
+        reused.tokenize();
+        let mut fresh = Scanner::new(SOURCE);
+        fresh.tokenize();
+        let positions = |scanner: &Scanner| {
+            scanner
+                .tokens()
+                .iter()
+                .map(|token| (token.clone(), token.line, token.column))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(&reused), positions(&fresh));
+        assert_eq!(
+            reused
+                .diagnostics()
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            ["[line 5] Error: Unexpected character: @"]
+        );
     }
 }
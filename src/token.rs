@@ -1,20 +1,32 @@
 use std::{
+    cmp::Ordering,
+    collections::HashMap,
     fmt::Display,
+    hash::{Hash, Hasher},
     process::ExitCode,
     sync::{Arc, RwLock},
 };
 
+use crate::callable::{Class, Function, Instance, Native};
+use crate::config;
+use crate::diagnostics;
 use crate::env::Env;
+use crate::map::Map;
+use crate::output;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
-    //? Characters: (, ), {, }, ,, ., -, +, ;, *, =, ==, !, !=, <, <=, >, >=, /
+    //? Characters: (, ), {, }, [, ], ,, :, ., ..., -, +, ;, *, =, ==, !, !=, <, <=, >, >=, /, %, **, &, |, ^, <<, >>, +=, -=, *=, /=
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
+    DotDotDot,
     Minus,
     Plus,
     SemiColon,
@@ -28,14 +40,31 @@ pub enum TokenType {
     Greater,
     GreaterEqual,
     Slash,
+    Percent,
+    StarStar,
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
     //? Literals:
     String(String),
     Number(f64),
     //? Identifier
     Identifier,
-    //? Reserved Words: and, class, else, false, for, fun, if, nil, or, print, return, super, this, true, var, while
+    //? Reserved Words: and, break, case, class, const, continue, default, do, else, false, for, fun, if, nil, or, print, repeat, return, super, switch, this, true, var, while
     And,
+    Break,
+    Case,
     Class,
+    Const,
+    Continue,
+    Default,
+    Do,
     Else,
     False,
     For,
@@ -44,12 +73,16 @@ pub enum TokenType {
     Nil,
     Or,
     Print,
+    Repeat,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
     While,
+    //? Text the scanner reported an error for, kept so tools can see where it was.
+    Error,
     //? End of file
     Eof,
 }
@@ -61,8 +94,12 @@ impl Display for TokenType {
             TokenType::RightParen => write!(f, "RIGHT_PAREN"),
             TokenType::LeftBrace => write!(f, "LEFT_BRACE"),
             TokenType::RightBrace => write!(f, "RIGHT_BRACE"),
+            TokenType::LeftBracket => write!(f, "LEFT_BRACKET"),
+            TokenType::RightBracket => write!(f, "RIGHT_BRACKET"),
             TokenType::Comma => write!(f, "COMMA"),
+            TokenType::Colon => write!(f, "COLON"),
             TokenType::Dot => write!(f, "DOT"),
+            TokenType::DotDotDot => write!(f, "DOT_DOT_DOT"),
             TokenType::Minus => write!(f, "MINUS"),
             TokenType::Plus => write!(f, "PLUS"),
             TokenType::SemiColon => write!(f, "SEMICOLON"),
@@ -76,11 +113,28 @@ impl Display for TokenType {
             TokenType::Greater => write!(f, "GREATER"),
             TokenType::GreaterEqual => write!(f, "GREATER_EQUAL"),
             TokenType::Slash => write!(f, "SLASH"),
+            TokenType::Percent => write!(f, "PERCENT"),
+            TokenType::StarStar => write!(f, "STAR_STAR"),
+            TokenType::Ampersand => write!(f, "AMPERSAND"),
+            TokenType::Pipe => write!(f, "PIPE"),
+            TokenType::Caret => write!(f, "CARET"),
+            TokenType::LessLess => write!(f, "LESS_LESS"),
+            TokenType::GreaterGreater => write!(f, "GREATER_GREATER"),
+            TokenType::PlusEqual => write!(f, "PLUS_EQUAL"),
+            TokenType::MinusEqual => write!(f, "MINUS_EQUAL"),
+            TokenType::StarEqual => write!(f, "STAR_EQUAL"),
+            TokenType::SlashEqual => write!(f, "SLASH_EQUAL"),
             TokenType::String(_) => write!(f, "STRING"),
             TokenType::Number(_) => write!(f, "NUMBER"),
             TokenType::Identifier => write!(f, "IDENTIFIER"),
             TokenType::And => write!(f, "AND"),
+            TokenType::Break => write!(f, "BREAK"),
+            TokenType::Case => write!(f, "CASE"),
             TokenType::Class => write!(f, "CLASS"),
+            TokenType::Const => write!(f, "CONST"),
+            TokenType::Continue => write!(f, "CONTINUE"),
+            TokenType::Default => write!(f, "DEFAULT"),
+            TokenType::Do => write!(f, "DO"),
             TokenType::Else => write!(f, "ELSE"),
             TokenType::False => write!(f, "FALSE"),
             TokenType::For => write!(f, "FOR"),
@@ -89,23 +143,91 @@ impl Display for TokenType {
             TokenType::Nil => write!(f, "NIL"),
             TokenType::Or => write!(f, "OR"),
             TokenType::Print => write!(f, "PRINT"),
+            TokenType::Repeat => write!(f, "REPEAT"),
             TokenType::Return => write!(f, "RETURN"),
             TokenType::Super => write!(f, "SUPER"),
+            TokenType::Switch => write!(f, "SWITCH"),
             TokenType::This => write!(f, "THIS"),
             TokenType::True => write!(f, "TRUE"),
             TokenType::Var => write!(f, "VAR"),
             TokenType::While => write!(f, "WHILE"),
+            TokenType::Error => write!(f, "ERROR"),
             TokenType::Eof => write!(f, "EOF"),
         }
     }
 }
 
+impl TokenType {
+    //? Coarse grouping for `--tokens-only`: keywords (including `true`, `false` and `nil`),
+    //? identifiers, literals, punctuation for brackets and separators, and operators for
+    //? everything else the source spells out.
+    pub fn category(&self) -> &'static str {
+        match self {
+            TokenType::String(_) | TokenType::Number(_) => "literals",
+            TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::LeftBrace
+            | TokenType::RightBrace
+            | TokenType::LeftBracket
+            | TokenType::RightBracket
+            | TokenType::Comma
+            | TokenType::Colon
+            | TokenType::Dot
+            | TokenType::DotDotDot
+            | TokenType::SemiColon => "punctuation",
+            TokenType::Identifier => "identifiers",
+            TokenType::And
+            | TokenType::Break
+            | TokenType::Case
+            | TokenType::Class
+            | TokenType::Const
+            | TokenType::Continue
+            | TokenType::Default
+            | TokenType::Do
+            | TokenType::Else
+            | TokenType::False
+            | TokenType::For
+            | TokenType::Fun
+            | TokenType::If
+            | TokenType::Nil
+            | TokenType::Or
+            | TokenType::Print
+            | TokenType::Repeat
+            | TokenType::Return
+            | TokenType::Super
+            | TokenType::Switch
+            | TokenType::This
+            | TokenType::True
+            | TokenType::Var
+            | TokenType::While => "keywords",
+            TokenType::Error => "errors",
+            TokenType::Eof => "end",
+            _ => "operators",
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize,
+    pub column: usize,
+}
+
+//? Tokens compare by what they spell, not where, so equal trees parse from any layout.
+//? Number literals compare by bits, so a `NaN` literal still equals itself.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        let same_type = match (&self.token_type, &other.token_type) {
+            (TokenType::Number(left), TokenType::Number(right)) => {
+                left.to_bits() == right.to_bits()
+            }
+            (left, right) => left == right,
+        };
+        same_type && self.lexeme == other.lexeme
+    }
 }
 
 impl Display for Token {
@@ -118,12 +240,28 @@ impl Display for Token {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Binary(Box<Expr>, Token, Box<Expr>),
     Literal(Token),
+    //? Resolved scope distance, `None` for globals.
+    Variable(Token, Option<usize>),
     Unary(Token, Box<Expr>),
-    Group(Box<Stmt>),
+    Group(Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Get(Box<Expr>, Token),
+    Set(Box<Expr>, Token, Box<Expr>),
+    List(Vec<Expr>),
+    //? The `{` token locates invalid key errors.
+    Map(Token, Vec<(Expr, Expr)>),
+    //? The token is the `[`, kept for error locations.
+    Index(Box<Expr>, Token, Box<Expr>),
+    SetIndex(Box<Expr>, Token, Box<Expr>, Box<Expr>),
+    //? Assignment nested inside another assignment, e.g. the `b = 1` in `a.x = b = 1`.
+    Assign(Token, Box<Expr>, Option<usize>),
+    //? `name op= value`, with the operator token kept for the arithmetic and its errors.
+    Compound(Token, Token, Box<Expr>, Option<usize>),
+    Super(Token, Option<usize>),
 }
 
 impl Display for Expr {
@@ -138,30 +276,289 @@ impl Display for Expr {
                 _ => write!(f, "{}", token.lexeme),
             },
             Expr::Unary(operator, expr) => write!(f, "({} {})", operator.lexeme, expr),
-            Expr::Group(stmt) => write!(f, "(group {})", stmt),
+            Expr::Group(expr) => write!(f, "(group {})", expr),
+            Expr::Call(callee, arguments) => {
+                write!(f, "(call {}", callee)?;
+                for argument in arguments {
+                    write!(f, " {}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Get(object, name) => write!(f, "(get {} {})", object, name.lexeme),
+            Expr::Set(object, name, value) => {
+                write!(f, "(set {} {} {})", object, name.lexeme, value)
+            }
+            Expr::List(elements) => {
+                write!(f, "(list")?;
+                for element in elements {
+                    write!(f, " {}", element)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Map(_, entries) => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " {} {}", key, value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Index(object, _, index) => write!(f, "(index {} {})", object, index),
+            Expr::SetIndex(object, _, index, value) => {
+                write!(f, "(set-index {} {} {})", object, index, value)
+            }
+            Expr::Assign(name, value, _) => write!(f, "(assign {} {})", name.lexeme, value),
+            Expr::Compound(name, operator, value, _) => {
+                write!(f, "({} {} {})", operator.lexeme, name.lexeme, value)
+            }
+            Expr::Variable(name, _) => write!(f, "{}", name.lexeme),
+            Expr::Super(method, _) => write!(f, "(super {})", method.lexeme),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
     Boolean(bool),
-    String(String),
+    String(Text),
     Nil,
+    Function(Arc<Function>),
+    Native(Native),
+    Class(Arc<Class>),
+    Instance(Arc<RwLock<Instance>>),
+    List(Arc<RwLock<Vec<Value>>>),
+    Map(Arc<RwLock<Map>>),
+}
+
+//? A string value. Long ones hold an `Allocation` for as long as they, or a copy, live.
+#[derive(Debug, Clone)]
+pub struct Text {
+    text: String,
+    _allocation: Option<config::Allocation>,
+}
+
+impl Text {
+    pub fn new(text: String) -> Result<Self, ExitCode> {
+        let allocation = if text.len() >= config::TRACKED_STRING_BYTES {
+            Some(config::Allocation::new()?)
+        } else {
+            None
+        };
+        Ok(Text {
+            text,
+            _allocation: allocation,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl std::ops::Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+//? Numbers compare by IEEE value, so `NaN == NaN` is false and `NaN != NaN` is true.
+//? Values of different types are never equal (`nil == 0` is false); references compare by
+//? identity.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            (Value::String(left), Value::String(right)) => left.as_str() == right.as_str(),
+            (Value::Nil, Value::Nil) => true,
+            (Value::Function(left), Value::Function(right)) => Arc::ptr_eq(left, right),
+            (Value::Native(left), Value::Native(right)) => left.name == right.name,
+            (Value::Class(left), Value::Class(right)) => Arc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Arc::ptr_eq(left, right),
+            (Value::List(left), Value::List(right)) => Arc::ptr_eq(left, right),
+            (Value::Map(left), Value::Map(right)) => Arc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+//? Needed for map keys. `NaN` breaks reflexivity, which is why maps refuse it as a key.
+impl Eq for Value {}
+
+//? Hashes the variant plus its payload. Numbers hash their bit pattern with `-0` folded into
+//? `0`, matching `==`. Only strings and (non-NaN) numbers are valid map keys; other variants
+//? hash by variant alone and are rejected by maps with a runtime error.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Number(n) => (n + 0.0).to_bits().hash(state),
+            Value::String(s) => s.as_str().hash(state),
+            _ => {}
+        }
+    }
+}
+
+//? Only numbers and strings are ordered; every other pairing (including `nil` with itself)
+//? is incomparable.
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left.partial_cmp(right),
+            (Value::String(left), Value::String(right)) => {
+                left.as_str().partial_cmp(right.as_str())
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_visiting(f, &mut vec![])
+    }
+}
+
+impl Value {
+    //? `visiting` holds the lists and maps being printed further up, so one that contains
+    //? itself prints as `[...]` or `{...}` where it recurs instead of overflowing the stack.
+    fn fmt_visiting(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        visiting: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
         match self {
-            Value::Number(n) => write!(f, "{}", n),
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "{}", s),
             Value::Nil => write!(f, "nil"),
+            Value::Function(function) => write!(f, "<fn {}>", function.name),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::Class(class) => write!(f, "<class {}>", class.name),
+            Value::Instance(instance) => {
+                write!(f, "{} instance", instance.read().unwrap().class.name)
+            }
+            Value::List(list) => {
+                let pointer = Arc::as_ptr(list) as *const ();
+                if visiting.contains(&pointer) {
+                    return write!(f, "[...]");
+                }
+                visiting.push(pointer);
+                write!(f, "[")?;
+                for (i, element) in list.read().unwrap().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_visiting(f, visiting)?;
+                }
+                visiting.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let pointer = Arc::as_ptr(map) as *const ();
+                if visiting.contains(&pointer) {
+                    return write!(f, "{{...}}");
+                }
+                visiting.push(pointer);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.read().unwrap().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_visiting(f, visiting)?;
+                }
+                visiting.pop();
+                write!(f, "}}")
+            }
         }
     }
 }
 
+//? Whole numbers print without a fraction (`5`), others use the shortest representation
+//? that round-trips (`2.5`, `3.14`).
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n.fract() == 0.0 {
+        format!("{:.0}", n)
+    } else {
+        format!("{}", n)
+    }
+}
+
+impl Value {
+    pub fn string(text: impl Into<String>) -> Result<Value, ExitCode> {
+        Ok(Value::String(Text::new(text.into())?))
+    }
+
+    //? `false` and `nil` are falsey; everything else, including `0` and `""`, is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Boolean(false) | Value::Nil)
+    }
+
+    //? Also what `typeof` returns, so scripts depend on these names staying put.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Nil => "nil",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+
+    pub fn print(&self) {
+        Self::print_all(std::slice::from_ref(self));
+    }
+
+    //? One line with the values separated by single spaces.
+    pub fn print_all(values: &[Value]) {
+        let line = values
+            .iter()
+            .map(|value| {
+                if config::show_types() {
+                    format!("{} : {}", value, value.type_name())
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        output::write(format_args!("{}{}", line, config::line_ending()))
+            .expect("failed printing to stdout");
+    }
+}
+
+//? Non-local exits that unwind through statement evaluation.
+#[derive(Debug)]
+pub enum Interrupt {
+    Error(ExitCode),
+    Return(Value),
+    Break,
+    Continue,
+}
+
+impl From<ExitCode> for Interrupt {
+    fn from(exitcode: ExitCode) -> Self {
+        Interrupt::Error(exitcode)
+    }
+}
+
 impl Expr {
     pub fn evaluate(&self, environment: Arc<RwLock<Env>>) -> Result<Value, ExitCode> {
         match self {
@@ -169,95 +566,217 @@ impl Expr {
                 let left = left.evaluate(environment.clone())?;
                 match operator.token_type {
                     TokenType::Or => {
-                        if let Value::Boolean(true) | Value::Number(_) | Value::String(_) = left {
+                        if left.is_truthy() {
                             return Ok(left);
                         } else {
                             return right.evaluate(environment);
                         }
                     }
                     TokenType::And => {
-                        if let Value::Boolean(false) | Value::Nil = left {
-                            return Ok(left);
-                        } else {
+                        if left.is_truthy() {
                             return right.evaluate(environment);
+                        } else {
+                            return Ok(left);
                         }
                     }
                     _ => {}
                 }
 
                 let right = right.evaluate(environment.clone())?;
-                match (&operator.token_type, &left, &right) {
-                    (TokenType::Or, left, right) => match (left, right) {
-                        (Value::Boolean(false) | Value::Nil, _) => Ok(right.clone()),
-                        (Value::Boolean(true) | Value::Number(_) | Value::String(_), _) => {
-                            Ok(left.clone())
-                        }
+                binary(&operator.token_type, operator, &left, &right)
+            }
+            Expr::Group(expr) => expr.evaluate(environment),
+            Expr::Call(callee, arguments) => {
+                let callee = callee.evaluate(environment.clone())?;
+                let mut values = vec![];
+                for argument in arguments {
+                    values.push(argument.evaluate(environment.clone())?);
+                }
+
+                //? The fewest and, unless variadic, the most arguments the callee takes.
+                let (min, max) = match &callee {
+                    Value::Function(function) => match function.rest {
+                        Some(_) => (function.arity(), None),
+                        None => (function.arity(), Some(function.arity())),
                     },
-                    (TokenType::Plus, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Number(left + right))
-                    }
-                    (TokenType::Plus, Value::String(left), Value::String(right)) => {
-                        Ok(Value::String(left.to_owned() + right))
-                    }
-                    (TokenType::Plus, _, _) => {
-                        eprintln!("Operands must be two numbers or two strings.");
-                        Err(ExitCode::from(70))
-                    }
-                    (TokenType::Minus, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Number(left - right))
+                    Value::Native(native) => {
+                        (native.arity, native.arity.checked_add(native.optional))
                     }
-                    (TokenType::Star, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Number(left * right))
+                    Value::Class(_) => (0, Some(0)),
+                    _ => {
+                        eprintln!("Can only call functions and classes.");
+                        return Err(ExitCode::from(70));
                     }
-                    (TokenType::Slash, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Number(left / right))
+                };
+                let count = values.len();
+                if count < min || max.is_some_and(|max| count > max) {
+                    match max {
+                        Some(max) if max == min => {
+                            eprintln!("Expected {} arguments but got {}.", min, count)
+                        }
+                        Some(max) => {
+                            eprintln!("Expected {} to {} arguments but got {}.", min, max, count)
+                        }
+                        None => eprintln!("Expected at least {} arguments but got {}.", min, count),
                     }
-                    (TokenType::Greater, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Boolean(left > right))
+                    return Err(ExitCode::from(70));
+                }
+
+                match callee {
+                    Value::Function(function) => function.call(values),
+                    Value::Native(native) => (native.function)(&values),
+                    Value::Class(class) => {
+                        config::track_allocation()?;
+                        Ok(Value::Instance(Instance::new(class)))
                     }
-                    (TokenType::GreaterEqual, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Boolean(left >= right))
+                    _ => unreachable!(),
+                }
+            }
+            Expr::Get(object, name) => match object.evaluate(environment)? {
+                Value::Instance(instance) => Instance::get(&instance, &name.lexeme),
+                _ => Err(runtime_error(name, "Only instances have properties.")),
+            },
+            Expr::Set(object, name, value) => match object.evaluate(environment.clone())? {
+                Value::Instance(instance) => {
+                    let value = value.evaluate(environment)?;
+                    instance
+                        .write()
+                        .unwrap()
+                        .set(name.lexeme.clone(), value.clone());
+                    Ok(value)
+                }
+                _ => Err(runtime_error(name, "Only instances have fields.")),
+            },
+            Expr::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(element.evaluate(environment.clone())?);
+                }
+                config::track_allocation()?;
+                Ok(Value::List(Arc::new(RwLock::new(values))))
+            }
+            Expr::Map(brace, entries) => {
+                let mut map = Map::default();
+                for (key, value) in entries {
+                    let key = map_key(brace, key.evaluate(environment.clone())?)?;
+                    map.insert(key, value.evaluate(environment.clone())?);
+                }
+                config::track_allocation()?;
+                Ok(Value::Map(Arc::new(RwLock::new(map))))
+            }
+            //? A missing map key reads as `nil`, like an unset variable in most scripting
+            //? languages; use `has()` to tell it apart from a stored `nil`.
+            Expr::Index(object, bracket, index) => {
+                let object = object.evaluate(environment.clone())?;
+                let index = index.evaluate(environment)?;
+                match object {
+                    Value::List(list) => {
+                        let list = list.read().unwrap();
+                        let position = list_position(bracket, &index, list.len())?;
+                        Ok(list[position].clone())
                     }
-                    (TokenType::Less, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Boolean(left < right))
+                    Value::Map(map) => {
+                        let key = map_key(bracket, index)?;
+                        Ok(map.read().unwrap().get(&key).cloned().unwrap_or(Value::Nil))
                     }
-                    (TokenType::LessEqual, Value::Number(left), Value::Number(right)) => {
-                        Ok(Value::Boolean(left <= right))
+                    _ => Err(runtime_error(
+                        bracket,
+                        "Only lists and maps can be indexed.",
+                    )),
+                }
+            }
+            Expr::SetIndex(object, bracket, index, value) => {
+                let object = object.evaluate(environment.clone())?;
+                let index = index.evaluate(environment.clone())?;
+                let value = value.evaluate(environment)?;
+                match object {
+                    Value::List(list) => {
+                        let mut list = list.write().unwrap();
+                        let position = list_position(bracket, &index, list.len())?;
+                        list[position] = value.clone();
                     }
-                    (
-                        TokenType::Minus
-                        | TokenType::Star
-                        | TokenType::Slash
-                        | TokenType::Greater
-                        | TokenType::GreaterEqual
-                        | TokenType::Less
-                        | TokenType::LessEqual,
-                        _,
-                        _,
-                    ) => {
-                        eprintln!("Operand must be a number.");
-                        Err(ExitCode::from(70))
+                    Value::Map(map) => {
+                        let key = map_key(bracket, index)?;
+                        if map.write().unwrap().insert(key, value.clone()) {
+                            config::track_allocation()?;
+                        }
                     }
-                    (TokenType::EqualEqual, left, right) => Ok(Value::Boolean(left == right)),
-                    (TokenType::BangEqual, left, right) => Ok(Value::Boolean(left != right)),
                     _ => {
-                        eprintln!("Unsupported binary expression.");
-                        Err(ExitCode::from(65))
+                        return Err(runtime_error(
+                            bracket,
+                            "Only lists and maps can be indexed.",
+                        ))
                     }
                 }
+                Ok(value)
+            }
+            Expr::Assign(name, value, depth) => {
+                let value = value.evaluate(environment.clone())?;
+                match depth {
+                    Some(distance) => environment.write().unwrap().assign_at(
+                        *distance,
+                        &name.lexeme,
+                        value.clone(),
+                    )?,
+                    None => environment
+                        .write()
+                        .unwrap()
+                        .assign_global(&name.lexeme, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Compound(name, operator, value, depth) => {
+                let current = match depth {
+                    Some(distance) => environment.read().unwrap().peek_at(*distance, &name.lexeme),
+                    None => environment.read().unwrap().peek_global(&name.lexeme),
+                };
+                let Some(current) = current else {
+                    eprintln!("Undefined assign variable '{}'.", name.lexeme);
+                    return Err(ExitCode::from(70));
+                };
+                let kind = match operator.token_type {
+                    TokenType::PlusEqual => TokenType::Plus,
+                    TokenType::MinusEqual => TokenType::Minus,
+                    TokenType::StarEqual => TokenType::Star,
+                    _ => TokenType::Slash,
+                };
+                let right = value.evaluate(environment.clone())?;
+                let value = binary(&kind, operator, &current, &right)?;
+                match depth {
+                    Some(distance) => environment.write().unwrap().assign_at(
+                        *distance,
+                        &name.lexeme,
+                        value.clone(),
+                    )?,
+                    None => environment
+                        .write()
+                        .unwrap()
+                        .assign_global(&name.lexeme, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Variable(name, depth) => lookup(&environment, &name.lexeme, *depth),
+            Expr::Super(method, depth) => {
+                let superclass = lookup(&environment, "super", *depth)?;
+                let this = lookup(&environment, "this", depth.map(|depth| depth - 1))?;
+                let Value::Class(superclass) = superclass else {
+                    unreachable!()
+                };
+                match superclass.find_method(&method.lexeme) {
+                    Some(function) => Ok(Value::Function(function.bind(this))),
+                    None => Err(runtime_error(
+                        method,
+                        &format!("Undefined property '{}'.", method.lexeme),
+                    )),
+                }
             }
-            Expr::Group(stmt) => stmt.evaluate(environment),
             Expr::Literal(token) => match &token.token_type {
                 TokenType::Number(n) => Ok(Value::Number(*n)),
-                TokenType::String(s) => Ok(Value::String(s.clone())),
+                TokenType::String(s) => Value::string(s.clone()),
                 TokenType::True => Ok(Value::Boolean(true)),
                 TokenType::False => Ok(Value::Boolean(false)),
                 TokenType::Nil => Ok(Value::Nil),
-                TokenType::Identifier => environment.read().unwrap().get(&token.lexeme),
-                _ => {
-                    eprintln!("Unsupported literal expression.");
-                    Err(ExitCode::from(65))
-                }
+                _ => unreachable!("literals are only built from values"),
             },
             Expr::Unary(operator, expr) => {
                 let expr = expr.evaluate(environment)?;
@@ -266,37 +785,241 @@ impl Expr {
                         if let Value::Number(n) = expr {
                             Ok(Value::Number(-n))
                         } else {
-                            eprintln!("Operand must be a number.");
-                            Err(ExitCode::from(70))
-                        }
-                    }
-                    TokenType::Bang => {
-                        if let Value::Boolean(b) = expr {
-                            Ok(Value::Boolean(!b))
-                        } else if let Value::Number(_) = expr {
-                            Ok(Value::Boolean(false))
-                        } else if let Value::Nil = expr {
-                            Ok(Value::Boolean(true))
-                        } else {
-                            eprintln!("Operand must be a number or boolean.");
-                            Err(ExitCode::from(65))
+                            Err(runtime_error(operator, "Operand must be a number."))
                         }
                     }
-                    _ => {
-                        eprintln!("Unsupported unary expression.");
-                        Err(ExitCode::from(65))
-                    }
+                    TokenType::Bang => Ok(Value::Boolean(!expr.is_truthy())),
+                    _ => Err(runtime_error(operator, "Unsupported unary expression.")),
                 }
             }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+//? Applies a non-short-circuit binary operator of `kind`; errors point at `operator`,
+//? which differs from `kind` for compound assignments like `+=`.
+fn binary(
+    kind: &TokenType,
+    operator: &Token,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, ExitCode> {
+    match (kind, left, right) {
+        (TokenType::Or, left, right) => match left.is_truthy() {
+            true => Ok(left.clone()),
+            false => Ok(right.clone()),
+        },
+        (TokenType::Plus, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left + right))
+        }
+        (TokenType::Plus, Value::String(left), Value::String(right)) => {
+            Value::string(left.to_string() + right)
+        }
+        (TokenType::Plus, left, right) => Err(operand_error(
+            operator,
+            "Operands must be two numbers or two strings",
+            left,
+            right,
+        )),
+        (TokenType::Minus, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left - right))
+        }
+        (TokenType::Star, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left * right))
+        }
+        (TokenType::Slash, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left / right))
+        }
+        //? Takes the sign of the dividend, as Rust's `%` does; unlike `/` by zero, which gives
+        //? an infinity, a zero divisor has no sensible result and is an error.
+        (TokenType::Percent, Value::Number(_), Value::Number(right)) if *right == 0.0 => {
+            Err(operator_error(operator, "Modulo by zero."))
+        }
+        (TokenType::Percent, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left % right))
+        }
+        (TokenType::StarStar, Value::Number(left), Value::Number(right)) => {
+            Ok(Value::Number(left.powf(*right)))
+        }
+        //? Numbers and strings order as `PartialOrd` says; booleans are deliberately unordered.
+        (
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual,
+            Value::Number(_),
+            Value::Number(_),
+        )
+        | (
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual,
+            Value::String(_),
+            Value::String(_),
+        ) => {
+            let ordering = left.partial_cmp(right);
+            Ok(Value::Boolean(match kind {
+                TokenType::Greater => ordering == Some(Ordering::Greater),
+                TokenType::GreaterEqual => {
+                    matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                }
+                TokenType::Less => ordering == Some(Ordering::Less),
+                _ => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            }))
+        }
+        (
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual,
+            left,
+            right,
+        ) => Err(operand_error(
+            operator,
+            "Operands must be numbers or strings",
+            left,
+            right,
+        )),
+        (
+            TokenType::Minus
+            | TokenType::Star
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::StarStar,
+            left,
+            right,
+        ) => Err(operand_error(
+            operator,
+            "Operands must be numbers",
+            left,
+            right,
+        )),
+        (
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater,
+            left,
+            right,
+        ) => bitwise(operator, left, right),
+        //? Both sides have already been evaluated, left first.
+        (TokenType::Comma, _, right) => Ok(right.clone()),
+        (TokenType::EqualEqual, left, right) => Ok(Value::Boolean(left == right)),
+        (TokenType::BangEqual, left, right) => Ok(Value::Boolean(left != right)),
+        _ => Err(operator_error(operator, "Unsupported binary expression.")),
+    }
+}
+
+//? Bitwise operators work on integral numbers as i64; shifts take a count from 0 to 63.
+fn bitwise(operator: &Token, left: &Value, right: &Value) -> Result<Value, ExitCode> {
+    let (Some(left), Some(right)) = (integer(left), integer(right)) else {
+        return Err(operator_error(operator, "Operands must be integers."));
+    };
+    let result = match operator.token_type {
+        TokenType::Ampersand => left & right,
+        TokenType::Pipe => left | right,
+        TokenType::Caret => left ^ right,
+        TokenType::LessLess | TokenType::GreaterGreater => {
+            let Some(count) = u32::try_from(right).ok().filter(|count| *count < 64) else {
+                return Err(operator_error(
+                    operator,
+                    "Shift count must be between 0 and 63.",
+                ));
+            };
+            if operator.token_type == TokenType::LessLess {
+                left << count
+            } else {
+                left >> count
+            }
+        }
+        _ => unreachable!(),
+    };
+    Ok(Value::Number(result as f64))
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= i64::MAX as f64 => Some(*n as i64),
+        _ => None,
+    }
+}
+
+fn map_key(token: &Token, key: Value) -> Result<Value, ExitCode> {
+    if Map::accepts(&key) {
+        Ok(key)
+    } else {
+        Err(runtime_error(token, "Map keys must be strings or numbers."))
+    }
+}
+
+fn list_position(bracket: &Token, index: &Value, len: usize) -> Result<usize, ExitCode> {
+    match index {
+        Value::Number(n) if n.fract() == 0.0 => {
+            if *n >= 0.0 && (*n as usize) < len {
+                Ok(*n as usize)
+            } else {
+                Err(runtime_error(
+                    bracket,
+                    &format!(
+                        "Index {} out of range for list of length {}.",
+                        format_number(*n),
+                        len
+                    ),
+                ))
+            }
+        }
+        _ => Err(runtime_error(bracket, "List index must be an integer.")),
+    }
+}
+
+fn parameters(params: &[String], rest: &Option<String>) -> String {
+    let mut parameters = params.to_vec();
+    if let Some(rest) = rest {
+        parameters.push(format!("...{}", rest));
+    }
+    parameters.join(", ")
+}
+
+//? Binary operator type errors name both operand types, e.g. "got string and number".
+fn operand_error(operator: &Token, expected: &str, left: &Value, right: &Value) -> ExitCode {
+    let message = format!(
+        "{}, got {} and {}.",
+        expected,
+        left.type_name(),
+        right.type_name()
+    );
+    operator_error(operator, &message)
+}
+
+//? Errors from binary operators lead with the operator's line.
+fn operator_error(operator: &Token, message: &str) -> ExitCode {
+    runtime_error(operator, &format!("[line {}] {}", operator.line, message))
+}
+
+//? Reports a runtime error, pointing at `token` when the program source is available.
+fn runtime_error(token: &Token, message: &str) -> ExitCode {
+    eprintln!("{}", message);
+    if let Some(snippet) =
+        config::source().and_then(|source| diagnostics::snippet(&source, token.line, token.column))
+    {
+        eprintln!("{}", snippet);
+    }
+    ExitCode::from(70)
+}
+
+fn lookup(
+    environment: &Arc<RwLock<Env>>,
+    name: &str,
+    depth: Option<usize>,
+) -> Result<Value, ExitCode> {
+    match depth {
+        Some(distance) => environment.read().unwrap().get_at(distance, name),
+        None => environment.read().unwrap().get_global(name),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     Block(Vec<Stmt>),
-    Print(Box<Stmt>),
+    //? `print a, b` holds one expression per printed value.
+    Print(Vec<Expr>),
     While(Box<Stmt>, Box<Stmt>),
+    //? Body, then the condition checked after each run of it.
+    DoWhile(Box<Stmt>, Box<Stmt>),
+    Repeat(Box<Stmt>, Box<Stmt>),
     For(
         Option<Box<Stmt>>,
         Option<Box<Stmt>>,
@@ -304,86 +1027,421 @@ pub enum Stmt {
         Box<Stmt>,
     ),
     If(Box<Stmt>, Box<Stmt>, Option<Box<Stmt>>),
-    Declare(String, Box<Stmt>),
-    Assign(String, Box<Stmt>),
+    //? Scrutinee, `case` arms in order, then the `default` arm. Arms don't fall through.
+    Switch(Expr, Vec<(Expr, Vec<Stmt>)>, Option<Vec<Stmt>>),
+    //? No initializer means `nil`; the flag marks `const` declarations.
+    Declare(Token, Option<Box<Stmt>>, bool),
+    Assign(String, Box<Stmt>, Option<usize>),
+    //? Name, fixed parameters, optional `...rest` parameter, body.
+    Function(String, Vec<String>, Option<String>, Arc<Vec<Stmt>>),
+    Class(String, Option<Expr>, Vec<Stmt>),
+    Return(Option<Expr>),
+    Break,
+    Continue,
     Expr(Expr),
 }
 
+//? Spaces added per nesting level; `pretty_with_indent` takes another width.
+const DEFAULT_INDENT_WIDTH: usize = 2;
+
 impl Display for Stmt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_nested(f, DEFAULT_INDENT_WIDTH, 0)
+    }
+}
+
+impl Stmt {
+    //? Like `Display`, but indents each nested body by `width` spaces per level.
+    #[allow(dead_code)]
+    pub fn pretty_with_indent(&self, width: usize) -> String {
+        let mut out = String::new();
+        self.fmt_nested(&mut out, width, 0)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    //? Renders the statement as an outline for the `ast` command: one node per line, each
+    //? body one level deeper than the statement that owns it, starting at `depth`.
+    //? Expressions and simple statements stay on one line as `Display` prints them.
+    pub fn pretty(&self, depth: usize) -> String {
+        let mut lines = vec![];
+        self.outline(depth, &mut lines);
+        lines.join("\n")
+    }
+
+    fn outline(&self, depth: usize, lines: &mut Vec<String>) {
         match self {
             Stmt::Block(stmts) => {
-                writeln!(f, "{{")?;
+                Self::line(lines, depth, "block".to_string());
                 for stmt in stmts {
-                    writeln!(f, "   {}", stmt)?;
+                    stmt.outline(depth + 1, lines);
+                }
+            }
+            Stmt::While(condition, body) => {
+                Self::line(lines, depth, format!("while {}", condition));
+                body.outline(depth + 1, lines);
+            }
+            Stmt::DoWhile(body, condition) => {
+                Self::line(lines, depth, format!("do while {}", condition));
+                body.outline(depth + 1, lines);
+            }
+            Stmt::Repeat(count, body) => {
+                Self::line(lines, depth, format!("repeat {}", count));
+                body.outline(depth + 1, lines);
+            }
+            Stmt::For(init, condition, increment, body) => {
+                let clauses = [init, condition, increment]
+                    .map(|clause| clause.as_ref().map_or(String::new(), |c| c.to_string()));
+                Self::line(lines, depth, format!("for ({})", clauses.join(";")));
+                body.outline(depth + 1, lines);
+            }
+            Stmt::If(condition, if_branch, else_branch) => {
+                Self::line(lines, depth, format!("if {}", condition));
+                Self::line(lines, depth + 1, "then".to_string());
+                if_branch.outline(depth + 2, lines);
+                if let Some(else_branch) = else_branch {
+                    Self::line(lines, depth + 1, "else".to_string());
+                    else_branch.outline(depth + 2, lines);
+                }
+            }
+            Stmt::Switch(value, cases, default) => {
+                Self::line(lines, depth, format!("switch {}", value));
+                let arms = cases
+                    .iter()
+                    .map(|(value, body)| (format!("case {}", value), body))
+                    .chain(default.iter().map(|body| ("default".to_string(), body)));
+                for (arm, body) in arms {
+                    Self::line(lines, depth + 1, arm);
+                    for stmt in body {
+                        stmt.outline(depth + 2, lines);
+                    }
+                }
+            }
+            Stmt::Declare(var, Some(value), constant)
+                if !matches!(value.as_ref(), Stmt::Expr(_)) =>
+            {
+                let keyword = if *constant { "const" } else { "var" };
+                Self::line(lines, depth, format!("{} {} =", keyword, var.lexeme));
+                value.outline(depth + 1, lines);
+            }
+            Stmt::Assign(var, value, _) if !matches!(value.as_ref(), Stmt::Expr(_)) => {
+                Self::line(lines, depth, format!("{} =", var));
+                value.outline(depth + 1, lines);
+            }
+            Stmt::Function(name, params, rest, body) => {
+                Self::line(
+                    lines,
+                    depth,
+                    format!("fun {}({})", name, parameters(params, rest)),
+                );
+                for stmt in body.iter() {
+                    stmt.outline(depth + 1, lines);
+                }
+            }
+            Stmt::Class(name, superclass, methods) => {
+                match superclass {
+                    Some(superclass) => {
+                        Self::line(lines, depth, format!("class {} < {}", name, superclass))
+                    }
+                    None => Self::line(lines, depth, format!("class {}", name)),
+                }
+                for method in methods {
+                    if let Stmt::Function(name, params, rest, body) = method {
+                        Self::line(
+                            lines,
+                            depth + 1,
+                            format!("{}({})", name, parameters(params, rest)),
+                        );
+                        for stmt in body.iter() {
+                            stmt.outline(depth + 2, lines);
+                        }
+                    }
+                }
+            }
+            Stmt::Print(_)
+            | Stmt::Declare(..)
+            | Stmt::Assign(..)
+            | Stmt::Return(_)
+            | Stmt::Break
+            | Stmt::Continue
+            | Stmt::Expr(_) => Self::line(lines, depth, self.to_string()),
+        }
+    }
+
+    fn fmt_body(
+        f: &mut impl std::fmt::Write,
+        stmts: &[Stmt],
+        width: usize,
+        depth: usize,
+    ) -> std::fmt::Result {
+        writeln!(f, "{{")?;
+        for stmt in stmts {
+            write!(f, "{:indent$}", "", indent = width * (depth + 1))?;
+            stmt.fmt_nested(f, width, depth + 1)?;
+            writeln!(f)?;
+        }
+        write!(f, "{:indent$}}}", "", indent = width * depth)
+    }
+
+    fn fmt_nested(
+        &self,
+        f: &mut impl std::fmt::Write,
+        width: usize,
+        depth: usize,
+    ) -> std::fmt::Result {
+        match self {
+            Stmt::Block(stmts) => Self::fmt_body(f, stmts, width, depth),
+            Stmt::Print(exprs) => {
+                write!(f, "print ")?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", expr)?;
                 }
-                writeln!(f, "}}")?;
                 Ok(())
             }
-            Stmt::Print(expr) => write!(f, "print {}", expr),
             Stmt::While(condition, body) => {
-                write!(f, "while {} {}", condition, body)
+                write!(f, "while ")?;
+                condition.fmt_nested(f, width, depth)?;
+                write!(f, " ")?;
+                body.fmt_nested(f, width, depth)
+            }
+            Stmt::DoWhile(body, condition) => {
+                write!(f, "do ")?;
+                body.fmt_nested(f, width, depth)?;
+                write!(f, " while ")?;
+                condition.fmt_nested(f, width, depth)
+            }
+            Stmt::Repeat(count, body) => {
+                write!(f, "repeat ")?;
+                count.fmt_nested(f, width, depth)?;
+                write!(f, " ")?;
+                body.fmt_nested(f, width, depth)
             }
             Stmt::For(init, condition, increment, body) => {
                 write!(f, "for (")?;
                 if let Some(init) = init {
-                    write!(f, "{}", init)?;
+                    init.fmt_nested(f, width, depth)?;
                 }
                 write!(f, ";")?;
                 if let Some(condition) = condition {
-                    write!(f, "{}", condition)?;
+                    condition.fmt_nested(f, width, depth)?;
                 }
                 write!(f, ";")?;
                 if let Some(increment) = increment {
-                    write!(f, "{}", increment)?;
+                    increment.fmt_nested(f, width, depth)?;
                 }
-                write!(f, ") {}", body)
+                write!(f, ") ")?;
+                body.fmt_nested(f, width, depth)
             }
             Stmt::If(condition, if_branch, else_branch) => {
-                write!(f, "if {} {}", condition, if_branch).and_then(|_| {
-                    if let Some(else_branch) = else_branch {
-                        write!(f, " else {}", else_branch)
-                    } else {
-                        Ok(())
+                write!(f, "if ")?;
+                condition.fmt_nested(f, width, depth)?;
+                write!(f, " ")?;
+                if_branch.fmt_nested(f, width, depth)?;
+                //? A nested `Stmt::If` prints inline at the same depth, so a chain reads
+                //? `if .. else if .. else ..` rather than nesting a level per branch.
+                if let Some(else_branch) = else_branch {
+                    write!(f, " else ")?;
+                    else_branch.fmt_nested(f, width, depth)?;
+                }
+                Ok(())
+            }
+            Stmt::Switch(value, cases, default) => {
+                writeln!(f, "switch {} {{", value)?;
+                let arms = cases
+                    .iter()
+                    .map(|(value, body)| (Some(value), body))
+                    .chain(default.iter().map(|body| (None, body)));
+                for (value, body) in arms {
+                    write!(f, "{:indent$}", "", indent = width * (depth + 1))?;
+                    match value {
+                        Some(value) => writeln!(f, "case {}:", value)?,
+                        None => writeln!(f, "default:")?,
+                    }
+                    for stmt in body {
+                        write!(f, "{:indent$}", "", indent = width * (depth + 2))?;
+                        stmt.fmt_nested(f, width, depth + 2)?;
+                        writeln!(f)?;
+                    }
+                }
+                write!(f, "{:indent$}}}", "", indent = width * depth)
+            }
+            Stmt::Declare(var, stmt, constant) => {
+                let keyword = if *constant { "const" } else { "var" };
+                write!(f, "{} {}", keyword, var.lexeme)?;
+                match stmt {
+                    Some(stmt) => {
+                        write!(f, " = ")?;
+                        stmt.fmt_nested(f, width, depth)
                     }
-                })
+                    None => Ok(()),
+                }
+            }
+            Stmt::Assign(var, stmt, _) => {
+                write!(f, "{} = ", var)?;
+                stmt.fmt_nested(f, width, depth)
             }
-            Stmt::Declare(var, expr) => write!(f, "var {} = {}", var, expr),
-            Stmt::Assign(var, expr) => write!(f, "{} = {}", var, expr),
+            Stmt::Function(name, params, rest, body) => {
+                write!(f, "fun {}({}) ", name, parameters(params, rest))?;
+                Self::fmt_body(f, body, width, depth)
+            }
+            Stmt::Class(name, superclass, methods) => {
+                match superclass {
+                    Some(superclass) => writeln!(f, "class {} < {} {{", name, superclass)?,
+                    None => writeln!(f, "class {} {{", name)?,
+                }
+                for method in methods {
+                    write!(f, "{:indent$}", "", indent = width * (depth + 1))?;
+                    if let Stmt::Function(name, params, rest, body) = method {
+                        write!(f, "{}({}) ", name, parameters(params, rest))?;
+                        Self::fmt_body(f, body, width, depth + 1)?;
+                    }
+                    writeln!(f)?;
+                }
+                write!(f, "{:indent$}}}", "", indent = width * depth)
+            }
+            Stmt::Return(value) => match value {
+                Some(value) => write!(f, "return {}", value),
+                None => write!(f, "return"),
+            },
+            Stmt::Break => write!(f, "break"),
+            Stmt::Continue => write!(f, "continue"),
             Stmt::Expr(expr) => write!(f, "{}", expr),
         }
     }
-}
 
-impl Stmt {
-    pub fn evaluate_no_run(&self) -> Result<Value, ExitCode> {
+    fn line(lines: &mut Vec<String>, depth: usize, text: String) {
+        lines.push(format!(
+            "{:indent$}{}",
+            "",
+            text,
+            indent = DEFAULT_INDENT_WIDTH * depth
+        ));
+    }
+
+    //? Blocks that declare nothing share the enclosing environment; the resolver skips
+    //? their scope too so distances stay in sync.
+    pub fn declares_names(stmts: &[Stmt]) -> bool {
+        stmts.iter().any(Stmt::declares_name)
+    }
+
+    //? Whether running this can define a name in the environment it runs in, including from
+    //? `if`, loop and `for` clause bodies. Blocks and switch arms get their own.
+    fn declares_name(&self) -> bool {
         match self {
-            Stmt::Expr(expr) => {
-                let value = expr.evaluate(Env::new())?;
-                println!("{}", value);
-                Ok(value)
-            }
-            _ => Err(ExitCode::from(65)),
+            Stmt::Declare(..) | Stmt::Function(..) | Stmt::Class(..) => true,
+            Stmt::Assign(_, value, _) => value.declares_name(),
+            Stmt::While(first, second)
+            | Stmt::DoWhile(first, second)
+            | Stmt::Repeat(first, second) => first.declares_name() || second.declares_name(),
+            Stmt::For(init, condition, increment, body) => [init, condition, increment]
+                .into_iter()
+                .flatten()
+                .chain([body])
+                .any(|stmt| stmt.declares_name()),
+            Stmt::If(condition, if_branch, else_branch) => [condition, if_branch]
+                .into_iter()
+                .chain(else_branch)
+                .any(|stmt| stmt.declares_name()),
+            Stmt::Block(_)
+            | Stmt::Switch(..)
+            | Stmt::Print(_)
+            | Stmt::Return(_)
+            | Stmt::Break
+            | Stmt::Continue
+            | Stmt::Expr(_) => false,
         }
     }
 
-    pub fn evaluate(&self, environment: Arc<RwLock<Env>>) -> Result<Value, ExitCode> {
+    //? Evaluates with everything the statement prints, natives included, written to `out`.
+    pub fn evaluate_with_writer(
+        &self,
+        environment: Arc<RwLock<Env>>,
+        out: output::Sink,
+    ) -> Result<Value, Interrupt> {
+        output::with_sink(out, || self.evaluate(environment))
+    }
+
+    fn evaluate_block(
+        statements: &[Stmt],
+        environment: Arc<RwLock<Env>>,
+    ) -> Result<Value, Interrupt> {
+        let block_environment = if Stmt::declares_names(statements) {
+            Env::with_enclosing(environment)
+        } else {
+            environment
+        };
+        for stmt in statements {
+            stmt.evaluate(block_environment.clone())?;
+        }
+        Ok(Value::Nil)
+    }
+
+    //? Runs one pass of a loop body: `Ok(false)` once it hit `break`, `Ok(true)` to go on,
+    //? including after `continue`.
+    fn evaluate_loop_body(&self, environment: Arc<RwLock<Env>>) -> Result<bool, Interrupt> {
+        match self.evaluate(environment) {
+            Ok(_) | Err(Interrupt::Continue) => Ok(true),
+            Err(Interrupt::Break) => Ok(false),
+            Err(interrupt) => Err(interrupt),
+        }
+    }
+
+    pub fn evaluate(&self, environment: Arc<RwLock<Env>>) -> Result<Value, Interrupt> {
         match self {
-            Stmt::Block(statements) => {
-                let block_environment = Env::with_enclosing(environment);
-                for stmt in statements {
-                    stmt.evaluate(block_environment.clone())?;
+            Stmt::Block(statements) => Self::evaluate_block(statements, environment),
+            Stmt::Print(exprs) => {
+                let mut values = Vec::with_capacity(exprs.len());
+                for expr in exprs {
+                    values.push(expr.evaluate(environment.clone())?);
                 }
+                Value::print_all(&values);
                 Ok(Value::Nil)
             }
-            Stmt::Print(statement) => {
-                let value = statement.evaluate(environment)?;
-                println!("{}", value);
+            Stmt::While(condition, body) => {
+                let mut iteration = 0;
+                while condition.evaluate(environment.clone())?.is_truthy() {
+                    config::check_interrupt()?;
+                    iteration += 1;
+                    config::check_iterations(iteration)?;
+                    if !body.evaluate_loop_body(environment.clone())? {
+                        break;
+                    }
+                }
                 Ok(Value::Nil)
             }
-            Stmt::While(condition, body) => {
-                while let Ok(Value::Boolean(true)) = condition.evaluate(environment.clone()) {
-                    body.evaluate(environment.clone())?;
+            Stmt::DoWhile(body, condition) => {
+                let mut iteration = 0;
+                loop {
+                    config::check_interrupt()?;
+                    iteration += 1;
+                    config::check_iterations(iteration)?;
+                    if !body.evaluate_loop_body(environment.clone())? {
+                        break;
+                    }
+                    if !condition.evaluate(environment.clone())?.is_truthy() {
+                        break;
+                    }
+                }
+                Ok(Value::Nil)
+            }
+            Stmt::Repeat(count, body) => {
+                //? The count is evaluated once, truncated, and clamped at zero.
+                let count = match count.evaluate(environment.clone())? {
+                    Value::Number(n) => n.trunc().max(0.0) as usize,
+                    _ => {
+                        eprintln!("Repeat count must be a number.");
+                        return Err(Interrupt::Error(ExitCode::from(70)));
+                    }
+                };
+                for iteration in 1..=count {
+                    config::check_interrupt()?;
+                    config::check_iterations(iteration)?;
+                    if !body.evaluate_loop_body(environment.clone())? {
+                        break;
+                    }
                 }
                 Ok(Value::Nil)
             }
@@ -392,51 +1450,313 @@ impl Stmt {
                     init.evaluate(environment.clone())?;
                 }
 
+                let mut iteration = 0;
                 match condition {
                     Some(condition) => {
-                        while let Ok(Value::Boolean(true)) = condition.evaluate(environment.clone())
-                        {
-                            body.evaluate(environment.clone())?;
-                            if let Some(increment) = increment {
-                                increment.evaluate(environment.clone())?;
+                        while condition.evaluate(environment.clone())?.is_truthy() {
+                            config::check_interrupt()?;
+                            iteration += 1;
+                            config::check_iterations(iteration)?;
+                            if !body.evaluate_loop_body(environment.clone())? {
+                                break;
                             }
-                        }
-                    }
-                    None => {
-                        while let Ok(_) = body.evaluate(environment.clone()) {
                             if let Some(increment) = increment {
                                 increment.evaluate(environment.clone())?;
                             }
                         }
                     }
+                    None => loop {
+                        config::check_interrupt()?;
+                        iteration += 1;
+                        config::check_iterations(iteration)?;
+                        if !body.evaluate_loop_body(environment.clone())? {
+                            break;
+                        }
+                        if let Some(increment) = increment {
+                            increment.evaluate(environment.clone())?;
+                        }
+                    },
                 }
                 Ok(Value::Nil)
             }
-            Stmt::If(condition, if_branch, else_branch) => {
-                match condition.evaluate(environment.clone())? {
-                    Value::Boolean(true) | Value::Number(_) | Value::String(_) => {
-                        if_branch.evaluate(environment)
-                    }
-                    Value::Boolean(false) | Value::Nil => {
-                        if let Some(else_branch) = else_branch {
-                            else_branch.evaluate(environment)
-                        } else {
-                            Ok(Value::Nil)
-                        }
+            Stmt::Switch(value, cases, default) => {
+                let value = value.evaluate(environment.clone())?;
+                for (case, body) in cases {
+                    if case.evaluate(environment.clone())? == value {
+                        return Self::evaluate_block(body, environment);
                     }
                 }
+                match default {
+                    Some(body) => Self::evaluate_block(body, environment),
+                    None => Ok(Value::Nil),
+                }
             }
-            Stmt::Declare(var, expr) => {
-                let value = expr.evaluate(environment.clone())?;
-                environment.write().unwrap().define(var.clone(), value);
+            Stmt::If(condition, if_branch, else_branch) => {
+                if condition.evaluate(environment.clone())?.is_truthy() {
+                    if_branch.evaluate(environment)
+                } else if let Some(else_branch) = else_branch {
+                    else_branch.evaluate(environment)
+                } else {
+                    Ok(Value::Nil)
+                }
+            }
+            Stmt::Declare(var, expr, constant) => {
+                let value = match expr {
+                    Some(expr) => expr.evaluate(environment.clone())?,
+                    None => Value::Nil,
+                };
+                if *constant {
+                    environment
+                        .write()
+                        .unwrap()
+                        .define_constant(var.lexeme.clone(), value);
+                } else {
+                    environment
+                        .write()
+                        .unwrap()
+                        .define(var.lexeme.clone(), value);
+                }
                 Ok(Value::Nil)
             }
-            Stmt::Assign(var, expr) => {
+            Stmt::Assign(var, expr, depth) => {
                 let value = expr.evaluate(environment.clone())?;
-                environment.write().unwrap().assign(var, value.clone())?;
+                match depth {
+                    Some(distance) => {
+                        environment
+                            .write()
+                            .unwrap()
+                            .assign_at(*distance, var, value.clone())?
+                    }
+                    None => environment
+                        .write()
+                        .unwrap()
+                        .assign_global(var, value.clone())?,
+                }
                 Ok(value)
             }
-            Stmt::Expr(expr) => expr.evaluate(environment),
+            Stmt::Function(name, params, rest, body) => {
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    rest: rest.clone(),
+                    body: body.clone(),
+                    closure: environment.clone(),
+                };
+                environment
+                    .write()
+                    .unwrap()
+                    .define(name.clone(), Value::Function(Arc::new(function)));
+                Ok(Value::Nil)
+            }
+            Stmt::Class(name, superclass, declarations) => {
+                let superclass = match superclass {
+                    Some(superclass) => match superclass.evaluate(environment.clone())? {
+                        Value::Class(class) => Some(class),
+                        _ => {
+                            eprintln!("Superclass must be a class.");
+                            return Err(Interrupt::Error(ExitCode::from(70)));
+                        }
+                    },
+                    None => None,
+                };
+                let closure = match &superclass {
+                    Some(superclass) => {
+                        let closure = Env::with_enclosing(environment.clone());
+                        closure
+                            .write()
+                            .unwrap()
+                            .define("super".to_string(), Value::Class(superclass.clone()));
+                        closure
+                    }
+                    None => environment.clone(),
+                };
+
+                let mut methods = HashMap::new();
+                for declaration in declarations {
+                    if let Stmt::Function(method, params, rest, body) = declaration {
+                        let function = Function {
+                            name: method.clone(),
+                            params: params.clone(),
+                            rest: rest.clone(),
+                            body: body.clone(),
+                            closure: closure.clone(),
+                        };
+                        methods.insert(method.clone(), Arc::new(function));
+                    }
+                }
+                let class = Class {
+                    name: name.clone(),
+                    superclass,
+                    methods,
+                };
+                environment
+                    .write()
+                    .unwrap()
+                    .define(name.clone(), Value::Class(Arc::new(class)));
+                Ok(Value::Nil)
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => value.evaluate(environment)?,
+                    None => Value::Nil,
+                };
+                Err(Interrupt::Return(value))
+            }
+            Stmt::Break => Err(Interrupt::Break),
+            Stmt::Continue => Err(Interrupt::Continue),
+            Stmt::Expr(expr) => Ok(expr.evaluate(environment)?),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::natives;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        scanner.tokenize();
+        let mut parser = Parser::new(scanner.tokens());
+        parser.parse();
+        assert!(parser.diagnostics().is_empty());
+        let mut resolver = Resolver::with_known_globals(natives::names());
+        resolver.resolve(parser.statements());
+        assert!(resolver.diagnostics().is_empty());
+        parser.statements().to_vec()
+    }
+
+    #[test]
+    fn evaluate_with_writer_collects_program_output() {
+        let stmts = parse(
+            "var greeting = \"hi\";\n{ var n = 1 + 2; print n, greeting; }\nwrite(\"no newline\");",
+        );
+        let environment = natives::globals();
+        let out = Rc::new(RefCell::new(Vec::new()));
+        for stmt in &stmts {
+            assert!(stmt
+                .evaluate_with_writer(environment.clone(), out.clone())
+                .is_ok());
+        }
+        assert_eq!(out.borrow().as_slice(), b"3 hi\nno newline");
+    }
+
+    #[test]
+    fn callables_display_by_kind_and_name() {
+        let stmts = parse("fun f() {}\nclass C { m() {} }");
+        let environment = natives::globals();
+        for stmt in &stmts {
+            assert!(stmt.evaluate(environment.clone()).is_ok());
+        }
+        let display = |name: &str| environment.read().unwrap().get(name).unwrap().to_string();
+        assert_eq!(display("f"), "<fn f>");
+        assert_eq!(display("len"), "<native fn len>");
+        assert_eq!(display("C"), "<class C>");
+    }
+
+    #[test]
+    fn functions_and_bound_methods_share_their_body() {
+        let stmts = parse("fun f() { print 1; }\nclass C { m() { print 2; } }\nvar c = C();");
+        let environment = natives::globals();
+        for stmt in &stmts {
+            assert!(stmt.evaluate(environment.clone()).is_ok());
+        }
+        let [Stmt::Function(_, _, _, declared), ..] = stmts.as_slice() else {
+            panic!("expected a function declaration first");
+        };
+        let Ok(Value::Function(f)) = environment.read().unwrap().get("f") else {
+            panic!("expected f to be a function");
+        };
+        assert!(Arc::ptr_eq(&f.body, declared));
+
+        let Ok(Value::Class(class)) = environment.read().unwrap().get("C") else {
+            panic!("expected C to be a class");
+        };
+        let method = class.find_method("m").unwrap();
+        let instance = environment.read().unwrap().get("c").unwrap();
+        assert!(Arc::ptr_eq(&method.bind(instance).body, &method.body));
+    }
+
+    #[test]
+    fn value_equality_follows_ieee_and_never_crosses_types() {
+        let nan = Value::Number(f64::NAN);
+        assert_ne!(nan, nan.clone());
+        assert_eq!(Value::Nil, Value::Nil);
+        assert_ne!(Value::Nil, Value::Number(0.0));
+        assert_ne!(Value::Nil, Value::Boolean(false));
+        assert_eq!(Value::Number(1.0), Value::Number(1.0));
+        assert_eq!(Value::Number(-0.0), Value::Number(0.0));
+        assert_ne!(Value::string("1").unwrap(), Value::Number(1.0));
+        assert_eq!(Value::string("a").unwrap(), Value::string("a").unwrap());
+    }
+
+    #[test]
+    fn blocks_without_declarations_reuse_the_environment() {
+        let allocations = |body: &str| {
+            let stmts = parse(&format!(
+                "var total = 0;\nfor (var i = 0; i < 1000; i = i + 1) {body}"
+            ));
+            let environment = natives::globals();
+            crate::allocations::count(|| {
+                for stmt in &stmts {
+                    assert!(stmt.evaluate(environment.clone()).is_ok());
+                }
+            })
+            .1
+        };
+        let bare = allocations("total = total + i;");
+        assert_eq!(allocations("{ total = total + i; }"), bare);
+        //? A declaration needs a scope of its own on every pass.
+        let scoped = allocations("{ var step = i; total = total + step; }");
+        assert!(scoped >= bare + 1000, "{scoped} {bare}");
+    }
+
+    #[test]
+    fn nested_blocks_indent_one_width_per_level() {
+        let stmts = parse("{ var a = 1; { print a; { print 2; } } }");
+        let [block] = stmts.as_slice() else {
+            panic!("expected one block: {:?}", stmts);
+        };
+        assert_eq!(
+            block.to_string(),
+            "{\n  var a = 1.0\n  {\n    print a\n    {\n      print 2.0\n    }\n  }\n}"
+        );
+        assert_eq!(
+            block.pretty_with_indent(4),
+            "{\n    var a = 1.0\n    {\n        print a\n        {\n            print 2.0\n        }\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn values_order_numbers_and_strings_only_among_themselves() {
+        assert!(Value::Number(1.0) < Value::Number(2.0));
+        assert!(Value::string("apple").unwrap() < Value::string("banana").unwrap());
+        assert_eq!(
+            Value::Number(1.0).partial_cmp(&Value::string("1").unwrap()),
+            None
+        );
+        assert_eq!(Value::Nil.partial_cmp(&Value::Nil), None);
+        assert_eq!(
+            Value::Number(f64::NAN).partial_cmp(&Value::Number(1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn numbers_and_strings_are_distinct_map_keys() {
+        let mut map = crate::map::Map::default();
+        let text = |s: &str| Value::string(s).unwrap();
+        map.insert(Value::Number(1.0), text("number"));
+        map.insert(text("1"), text("string"));
+        map.insert(Value::Number(0.0), text("zero"));
+        map.insert(Value::Number(-0.0), text("negative zero"));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&Value::Number(1.0)), Some(&text("number")));
+        assert_eq!(map.get(&text("1")), Some(&text("string")));
+        assert_eq!(map.get(&Value::Number(0.0)), Some(&text("negative zero")));
+    }
+}
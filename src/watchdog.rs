@@ -0,0 +1,78 @@
+use std::{
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{config, natives};
+
+//? Runs `source` as a program with fresh globals, aborting with "Timed out." (exit code 70)
+//? once `timeout` elapses. For hosts embedding the interpreter; the CLI wraps its own
+//? multi-file run in `with_timeout` instead.
+#[allow(dead_code)]
+pub fn run_with_timeout(source: &str, timeout: Duration) -> ExitCode {
+    with_timeout(timeout, || {
+        crate::run(&[("<source>", source.to_string())], natives::globals())
+    })
+}
+
+//? Runs `f`, aborting the evaluation inside it once `timeout` elapses. The watchdog only
+//? raises this run's interrupt flag, and exits as soon as `f` returns and drops `done`.
+pub fn with_timeout(timeout: Duration, f: impl FnOnce() -> ExitCode) -> ExitCode {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let (done, finished) = mpsc::channel::<()>();
+    let flag = interrupted.clone();
+    let watchdog = thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let exitcode = config::with_interrupt(interrupted.clone(), f);
+    drop(done);
+    watchdog.join().unwrap();
+
+    if interrupted.load(Ordering::Relaxed) {
+        eprintln!("Timed out.");
+        return ExitCode::from(70);
+    }
+    exitcode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //? `ExitCode` has no accessor for its value, so compare what it prints.
+    fn same(left: ExitCode, right: ExitCode) -> bool {
+        format!("{:?}", left) == format!("{:?}", right)
+    }
+
+    #[test]
+    fn endless_loop_times_out() {
+        let exitcode = run_with_timeout("while (true) {}", Duration::from_millis(50));
+        assert!(same(exitcode, ExitCode::from(70)));
+    }
+
+    #[test]
+    fn fast_program_completes() {
+        let exitcode = run_with_timeout(
+            "var total = 0;\nrepeat (10) total = total + 1;",
+            Duration::from_secs(10),
+        );
+        assert!(same(exitcode, ExitCode::SUCCESS));
+    }
+
+    #[test]
+    fn runtime_errors_keep_their_exit_code() {
+        let exitcode = run_with_timeout("-nil;", Duration::from_secs(10));
+        assert!(same(exitcode, ExitCode::from(70)));
+        let exitcode = run_with_timeout("print;", Duration::from_secs(10));
+        assert!(same(exitcode, ExitCode::from(65)));
+    }
+}
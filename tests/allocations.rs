@@ -0,0 +1,38 @@
+mod common;
+
+use common::run;
+
+const LONG: &str = "0123456789012345678901234567890123456789012345678901234567890123";
+
+#[test]
+fn dropped_long_strings_give_their_count_back() {
+    let source = format!(
+        "var long = \"{}\";\nfor (var i = 0; i < 20; i = i + 1) {{ var s = long + \"x\"; }}\nprint \"done\";",
+        LONG
+    );
+    assert_eq!(
+        run("run", &source, &["--max-allocations", "5"]),
+        ("done\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn live_long_strings_hit_the_cap() {
+    let source = format!(
+        "var long = \"{}\";\nvar l = [];\nfor (var i = 0; i < 20; i = i + 1) push(l, long + \"x\");",
+        LONG
+    );
+    assert_eq!(
+        run("run", &source, &["--max-allocations", "5"]),
+        ("".into(), "Memory limit exceeded.\n".into(), 70)
+    );
+}
+
+#[test]
+fn short_strings_are_not_counted() {
+    let source = "var s = \"\";\nfor (var i = 0; i < 30; i = i + 1) s = s + \"ab\";\nprint len(s);";
+    assert_eq!(
+        run("run", source, &["--max-allocations", "5"]),
+        ("60\n".into(), "".into(), 0)
+    );
+}
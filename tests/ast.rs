@@ -0,0 +1,101 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn ast_indents_each_nesting_level() {
+    let source = r#"
+{
+  var a = 1;
+  if (a) {
+    while (a < 3) {
+      a = a + 1;
+    }
+  } else print a;
+}
+fun f(x) { return x; }
+"#;
+    let expected = "\
+block
+  var a = 1.0
+  if a
+    then
+      block
+        while (< a 3.0)
+          block
+            a = (+ a 1.0)
+    else
+      print a
+fun f(x)
+  return x
+";
+    assert_eq!(run("ast", source, &[]), (expected.into(), "".into(), 0));
+}
+
+#[test]
+fn ast_indents_bodies_that_are_not_blocks() {
+    let source = "for (var i = 0; i < 2; i = i + 1) if (i) print i; else { print 0; }";
+    let expected = "\
+for (var i = 0.0;(< i 2.0);i = (+ i 1.0))
+  if i
+    then
+      print i
+    else
+      block
+        print 0.0
+";
+    assert_eq!(run("ast", source, &[]), (expected.into(), "".into(), 0));
+}
+
+#[test]
+fn teach_prints_every_section() {
+    let expected = "\
+== Source ==
+if (true) print 1;
+== Tokens ==
+[line 1] IF if null
+[line 1] LEFT_PAREN ( null
+[line 1] TRUE true null
+[line 1] RIGHT_PAREN ) null
+[line 1] PRINT print null
+[line 1] NUMBER 1 1.0
+[line 1] SEMICOLON ; null
+[line 2] EOF  null
+== AST ==
+if true
+  then
+    print 1.0
+== Diagnostics ==
+(none)
+";
+    assert_eq!(
+        run("--teach", "if (true) print 1;\n", &[]),
+        (expected.into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn teach_keeps_going_after_a_scan_error() {
+    let expected = "\
+== Source ==
+print @;
+print 2;
+== Tokens ==
+[line 1] PRINT print null
+[line 1] SEMICOLON ; null
+[line 2] PRINT print null
+[line 2] NUMBER 2 2.0
+[line 2] SEMICOLON ; null
+[line 3] EOF  null
+== AST ==
+print 2.0
+== Diagnostics ==
+[line 1] Error: Unexpected character: @
+    print @;
+          ^
+";
+    assert_eq!(
+        run("--teach", "print @;\nprint 2;\n", &[]),
+        (expected.into(), "".into(), 65)
+    );
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn chained_fields_round_trip() {
+    let source = r#"
+class Node {}
+var a = Node();
+a.b = Node();
+a.b.c = "deep";
+print a.b.c;
+a.b.c = a.b.c + "er";
+print a.b.c;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("deep\ndeeper\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn properties_on_non_instances_are_runtime_errors() {
+    let (_, stderr, code) = run("run", "var x = 1;\nprint x.y;", &[]);
+    assert_eq!(
+        stderr.lines().next(),
+        Some("Only instances have properties.")
+    );
+    assert_eq!(code, 70);
+    let (_, stderr, code) = run("run", "var x = 1;\nx.y = 2;", &[]);
+    assert_eq!(stderr.lines().next(), Some("Only instances have fields."));
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn breakfast_methods_read_and_write_fields() {
+    let source = r#"
+class Breakfast {
+  cook(meat) { this.meat = meat; return this; }
+  serve(who) { return "Enjoy your " + this.meat + ", " + who + "."; }
+}
+var breakfast = Breakfast();
+print breakfast.cook("bacon").serve("reader");
+breakfast.meat = "ham";
+var serve = breakfast.serve;
+print serve("again");
+print breakfast;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        (
+            "Enjoy your bacon, reader.\nEnjoy your ham, again.\nBreakfast instance\n".into(),
+            "".into(),
+            0
+        )
+    );
+}
+
+#[test]
+fn fields_shadow_methods() {
+    let source = r#"
+class Breakfast { meat() { return "method"; } }
+var breakfast = Breakfast();
+print breakfast.meat();
+breakfast.meat = "field";
+print breakfast.meat;
+"#;
+    assert_eq!(run("run", source, &[]).0, "method\nfield\n");
+}
+
+#[test]
+fn callables_print_readably() {
+    let source = "fun f() {}\nclass C { m() {} }\nprint f, len, C, C().m, [f, len], str(C);";
+    assert_eq!(
+        run("run", source, &[]).0,
+        "<fn f> <native fn len> <class C> <fn m> [<fn f>, <native fn len>] <class C>\n"
+    );
+}
+
+#[test]
+fn methods_keep_mutating_the_same_fields() {
+    let source = r#"
+class Counter {
+  start() { this.count = 0; return this; }
+  increment() { this.count = this.count + 1; return this.count; }
+}
+var counter = Counter().start();
+counter.increment();
+counter.increment();
+var increment = counter.increment;
+print increment();
+print counter.count;
+var other = Counter().start();
+print other.increment(), counter.count;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("3\n3\n1 3\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn this_outside_a_class_is_a_parse_error() {
+    let (stdout, stderr, code) = run("run", "fun f() { return this; }", &[]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 1] Error at 'this': Can't use 'this' outside of a class.")
+    );
+}
+
+#[test]
+fn super_calls_the_superclass_method_with_the_same_this() {
+    let source = r#"
+class A {
+  describe() { return "A named " + this.name; }
+  greet() { return "hi"; }
+}
+class B < A {
+  describe() { return super.describe() + ", also a B"; }
+}
+var b = B();
+b.name = "x";
+print b.describe();
+print b.greet();
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("A named x, also a B\nhi\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn inheriting_from_a_non_class_is_a_runtime_error() {
+    let (_, stderr, code) = run("run", "var NotAClass = 1;\nclass C < NotAClass {}", &[]);
+    assert_eq!(stderr.lines().next(), Some("Superclass must be a class."));
+    assert_eq!(code, 70);
+}
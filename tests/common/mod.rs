@@ -0,0 +1,43 @@
+use std::{
+    fs,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+//? Runs the interpreter's `command` on `source` with `flags` after the filename, returning
+//? stdout, stderr and the exit code.
+pub fn run(command: &str, source: &str, flags: &[&str]) -> (String, String, i32) {
+    run_files(command, &[source], flags)
+}
+
+//? Like `run`, but writes each of `sources` to its own file and passes them all in order.
+pub fn run_files(command: &str, sources: &[&str], flags: &[&str]) -> (String, String, i32) {
+    let paths = sources
+        .iter()
+        .map(|source| {
+            let path = std::env::temp_dir().join(format!(
+                "lox-test-{}-{}.lox",
+                std::process::id(),
+                FILES.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::write(&path, source).unwrap();
+            path
+        })
+        .collect::<Vec<_>>();
+    let output = Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+        .arg(command)
+        .args(&paths)
+        .args(flags)
+        .output()
+        .unwrap();
+    for path in &paths {
+        fs::remove_file(path).unwrap();
+    }
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap_or(-1),
+    )
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn dangling_else_runs_with_the_inner_if() {
+    let source = r#"
+fun check(a, b) {
+  if (a) if (b) print "both"; else print "only a";
+}
+check(true, true);
+check(true, false);
+check(false, true);
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("both\nonly a\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn else_if_chain_without_braces() {
+    let source = r#"
+fun sign(n) {
+  if (n < 0) return "negative";
+  else if (n == 0) return "zero";
+  else return "positive";
+}
+print sign(-2), sign(0), sign(5);
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("negative zero positive\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn empty_blocks_run_as_bodies() {
+    let source = "{}\nvar i = 0;\nwhile ((i = i + 1) < 3) {}\nif (i == 3) {} else { print \"no\"; }\nprint i;";
+    assert_eq!(run("run", source, &[]), ("3\n".into(), "".into(), 0));
+}
+
+#[test]
+fn switch_runs_the_first_matching_case_or_default() {
+    let source = r#"
+fun name(n) {
+  switch (n) {
+    case 1: return "one";
+    case 1 + 1: return "two";
+    default: return "many";
+  }
+}
+print name(1), name(2), name(3);
+switch (1) {
+  case 1: print "a";
+  case 1: print "b";
+}
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("one two many\na\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn switch_without_a_match_or_default_does_nothing() {
+    let source = "var hits = 0;\nswitch (\"x\") {\n  case \"y\": hits = hits + 1;\n}\nprint hits;";
+    assert_eq!(run("run", source, &[]), ("0\n".into(), "".into(), 0));
+}
+
+#[test]
+fn print_joins_its_arguments_with_spaces() {
+    assert_eq!(
+        run(
+            "run",
+            "print 1, \"two\", 3;\nprint \"one\";\nprint nil, 1 + 1;",
+            &[]
+        ),
+        ("1 two 3\none\nnil 2\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn every_condition_agrees_on_truthiness() {
+    //? One row per value; each letter is whether a construct treated it as true.
+    let source = r#"
+fun t(b) { if (b) return "T"; return "F"; }
+var values = [0, "", nil, false, true, "a"];
+for (var i = 0; i < len(values); i = i + 1) {
+  var v = values[i];
+  var w = false;
+  while (v and !w) w = true;
+  var f = false;
+  for (; v and !f;) f = true;
+  var d = 0;
+  do d = d + 1; while (v and d < 2);
+  print t(v) + t(!!v) + t(v and true) + t(v or false) + t(w) + t(f) + t(d == 2);
+}
+"#;
+    assert_eq!(
+        run("run", source, &[]).0,
+        "TTTTTTT\nTTTTTTT\nFFFFFFF\nFFFFFFF\nTTTTTTT\nTTTTTTT\n"
+    );
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn entry_receives_the_script_arguments() {
+    let source = "fun main(args) {\n  print args;\n  return len(args);\n}";
+    assert_eq!(
+        run(
+            "run",
+            source,
+            &["--entry", "main", "--", "a", "--pure", "b c"]
+        ),
+        ("[a, --pure, b c]\n".into(), "".into(), 3)
+    );
+    assert_eq!(
+        run("run", source, &["--entry", "main"]),
+        ("[]\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn entry_without_parameters_ignores_the_arguments() {
+    let source = "fun main() {\n  print \"main\";\n  return 7;\n}";
+    assert_eq!(
+        run("run", source, &["--entry", "main", "--", "a"]),
+        ("main\n".into(), "".into(), 7)
+    );
+}
+
+#[test]
+fn entry_must_be_a_function_taking_at_most_one_parameter() {
+    assert_eq!(
+        run("run", "var main = 1;", &["--entry", "main"]),
+        ("".into(), "Undefined entry function 'main'.\n".into(), 70)
+    );
+    assert_eq!(
+        run("run", "fun main(a, b) {}", &["--entry", "main"]),
+        (
+            "".into(),
+            "Entry function 'main' takes 2 parameters; expected 0 or 1.\n".into(),
+            70
+        )
+    );
+}
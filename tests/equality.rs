@@ -0,0 +1,39 @@
+mod common;
+
+use common::run;
+
+fn output(source: &str) -> String {
+    run("run", source, &[]).0
+}
+
+#[test]
+fn nan_is_unequal_to_itself() {
+    assert_eq!(
+        output("var n = 0 / 0;\nprint n == n;\nprint n != n;"),
+        "false\ntrue\n"
+    );
+}
+
+#[test]
+fn nil_equals_only_nil() {
+    assert_eq!(
+        output("print nil == nil;\nprint nil == 0;\nprint nil == false;"),
+        "true\nfalse\nfalse\n"
+    );
+}
+
+#[test]
+fn numbers_compare_by_value() {
+    assert_eq!(
+        output("print 1 == 1.0;\nprint -0 == 0;\nprint 0.1 + 0.2 == 0.3;"),
+        "true\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn values_of_different_types_are_unequal() {
+    assert_eq!(
+        output("print \"1\" == 1;\nprint true == 1;\nprint \"\" != nil;"),
+        "false\nfalse\ntrue\n"
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::process::Command;
+
+use common::run;
+
+fn code(command: &str, source: &str) -> i32 {
+    run(command, source, &[]).2
+}
+
+//? Runs the interpreter with `args` as given, returning stderr and the exit code.
+fn invoke(args: &[&str]) -> (String, i32) {
+    let output = Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+#[test]
+fn missing_arguments_print_usage_and_fail() {
+    for (args, first_line) in [
+        (&[][..], None),
+        (&["run"][..], Some("Missing filename for 'run'.")),
+        (&["bogus", "x.lox"][..], Some("Unknown command: bogus")),
+    ] {
+        let (stderr, code) = invoke(args);
+        assert_eq!(code, 1, "{:?}", args);
+        let mut lines = stderr.lines();
+        if let Some(first_line) = first_line {
+            assert_eq!(lines.next(), Some(first_line));
+        }
+        assert!(lines.next().unwrap().starts_with("Usage: "), "{stderr}");
+        assert_eq!(
+            lines.next(),
+            Some("Commands: tokenize, tokenize-json, parse, ast, parse-json, evaluate, run, check-names, env-dump, round-trip, --tokens-only, --teach")
+        );
+    }
+}
+
+#[test]
+fn scan_errors_exit_with_65_in_every_command() {
+    for command in ["tokenize", "parse", "evaluate", "run"] {
+        assert_eq!(code(command, "print @;"), 65, "{}", command);
+    }
+    assert_eq!(code("evaluate", "\"abc"), 65);
+}
+
+#[test]
+fn parse_errors_exit_with_65() {
+    assert_eq!(code("tokenize", "print (1;"), 0);
+    for command in ["parse", "evaluate", "run"] {
+        assert_eq!(code(command, "(1 +"), 65, "{}", command);
+    }
+    assert_eq!(code("evaluate", ""), 65);
+    assert_eq!(code("run", "print var x = 1;"), 65);
+}
+
+#[test]
+fn resolve_errors_exit_with_65() {
+    assert_eq!(code("run", "{ var a = a; }"), 65);
+    assert_eq!(code("run", "print x;"), 65);
+}
+
+#[test]
+fn runtime_errors_exit_with_70() {
+    assert_eq!(code("parse", "-\"a\""), 0);
+    assert_eq!(code("evaluate", "-\"a\""), 70);
+    assert_eq!(code("evaluate", "x"), 70);
+    assert_eq!(code("run", "print 1 + nil;"), 70);
+    assert_eq!(code("run", "var l = [];\nprint l[0];"), 70);
+    assert_eq!(code("run", "fun f() {}\nf(1);"), 70);
+}
+
+#[test]
+fn exit_from_result_uses_a_small_whole_number() {
+    let exit = |last: &str| {
+        run(
+            "run",
+            &format!("print \"x\";\n{last};"),
+            &["--exit-from-result"],
+        )
+    };
+    assert_eq!(exit("2"), ("x\n".into(), "".into(), 2));
+    assert_eq!(exit("255").2, 255);
+    for last in ["256", "-1", "2.5", "\"2\"", "nil"] {
+        assert_eq!(exit(last).2, 0, "{}", last);
+    }
+    assert_eq!(code("run", "2;"), 0);
+}
+
+#[test]
+fn exit_flushes_output_and_ends_with_its_code() {
+    assert_eq!(
+        run("run", "write(\"a\");\nexit(3);\nprint \"no\";", &[]),
+        ("a".into(), "".into(), 3)
+    );
+    assert_eq!(code("run", "exit();"), 0);
+    assert_eq!(code("run", "exit(300);"), 255);
+    assert_eq!(code("run", "exit(-1);"), 0);
+    assert_eq!(code("run", "exit(2.9);"), 2);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn rest_parameter_collects_extra_arguments() {
+    let source = r#"
+fun sum(...nums) {
+  var total = 0;
+  for (var i = 0; i < len(nums); i = i + 1) total = total + nums[i];
+  return total;
+}
+print sum(), sum(1), sum(1, 2, 3);
+fun tag(label, ...rest) { print label, rest; }
+tag("a");
+tag("b", 1, 2);
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("0 1 6\na []\nb [1, 2]\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn rest_parameter_still_requires_the_fixed_ones() {
+    let source = "fun tag(label, ...rest) {}\ntag();";
+    assert_eq!(
+        run("run", source, &[]),
+        (
+            "".into(),
+            "Expected at least 1 arguments but got 0.\n".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow_error() {
+    let source = "fun f(n) { return f(n + 1); }\nf(0);";
+    assert_eq!(
+        run("run", source, &["--max-call-depth", "50"]),
+        ("".into(), "Stack overflow.\n".into(), 70)
+    );
+    //? The default limit catches it too, rather than crashing the process.
+    assert_eq!(run("run", source, &[]).2, 70);
+}
+
+#[test]
+fn recursion_within_the_limit_runs() {
+    let source = "fun f(n) { if (n == 0) return 0; return f(n - 1) + 1; }\nprint f(40);";
+    assert_eq!(
+        run("run", source, &["--max-call-depth", "50"]),
+        ("40\n".into(), "".into(), 0)
+    );
+}
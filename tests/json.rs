@@ -0,0 +1,53 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn tokenize_json_lists_every_token() {
+    let expected = r#"[
+  {"type": "VAR", "lexeme": "var", "line": 1},
+  {"type": "IDENTIFIER", "lexeme": "a", "line": 1},
+  {"type": "EQUAL", "lexeme": "=", "line": 1},
+  {"type": "STRING", "lexeme": "\"x\"", "line": 1, "literal": "x"},
+  {"type": "SEMICOLON", "lexeme": ";", "line": 1},
+  {"type": "PRINT", "lexeme": "print", "line": 2},
+  {"type": "NUMBER", "lexeme": "1.50", "line": 2, "literal": 1.5},
+  {"type": "SEMICOLON", "lexeme": ";", "line": 2},
+  {"type": "EOF", "lexeme": "", "line": 2}
+]
+"#;
+    assert_eq!(
+        run("tokenize-json", "var a = \"x\";\nprint 1.50;", &[]),
+        (expected.into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn parse_json_tags_every_node() {
+    let expected = r#"[
+  {"kind": "Declare", "name": "x", "constant": false, "value": {"kind": "Binary", "operator": "+", "left": {"kind": "Literal", "value": 1.0}, "right": {"kind": "Literal", "value": 2.0}}},
+  {"kind": "Block", "body": [{"kind": "If", "condition": {"kind": "Variable", "name": "x"}, "then": {"kind": "Print", "values": [{"kind": "Unary", "operator": "-", "right": {"kind": "Variable", "name": "x"}}]}, "else": null}]}
+]
+"#;
+    assert_eq!(
+        run("parse-json", "var x = 1 + 2;\n{ if (x) print -x; }", &[]),
+        (expected.into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn dump_env_json_holds_the_final_globals() {
+    let source = "var n = 1;\nvar s = \"a b\";\nvar l = [1, nil, true];\nvar m = {\"k\": 2};\nfun f() {}\nn = n + 1;\nprint n;";
+    let expected = r#"{
+  "n": 2.0,
+  "s": "a b",
+  "l": [1.0, null, true],
+  "m": {"k": 2.0},
+  "f": "<fn f>"
+}
+"#;
+    assert_eq!(
+        run("run", source, &["--dump-env-json"]),
+        ("2\n".into(), expected.into(), 0)
+    );
+}
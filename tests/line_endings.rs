@@ -0,0 +1,24 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn print_ends_lines_with_lf_by_default() {
+    assert_eq!(
+        run("run", "print 1;\nprint \"a\", 2;", &[]),
+        ("1\na 2\n".into(), "".into(), 0)
+    );
+    assert_eq!(run("run", "print 1;", &["--line-ending", "lf"]).0, "1\n");
+}
+
+#[test]
+fn crlf_line_ending_is_opt_in() {
+    assert_eq!(
+        run(
+            "run",
+            "print 1;\nwrite(\"a\");\nprint \"b\";",
+            &["--line-ending", "crlf"]
+        ),
+        ("1\r\nab\r\n".into(), "".into(), 0)
+    );
+}
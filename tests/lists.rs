@@ -0,0 +1,79 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn list_literals_print_their_elements() {
+    let source = "print [];\nprint [1, \"a\", [true, nil]];\nprint [1 + 1, -3,];";
+    assert_eq!(
+        run("run", source, &[]),
+        ("[]\n[1, a, [true, nil]]\n[2, -3]\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn index_reads_and_writes_elements() {
+    let source = r#"
+var l = [1, "a", [true, nil]];
+print l[0], l[2][0];
+l[1] = 2;
+l[2][1] = "set";
+print l;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("1 true\n[1, 2, [true, set]]\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn out_of_range_index_is_a_runtime_error() {
+    let source = "var l = [1, 2];\nprint l[2];";
+    assert_eq!(
+        run("run", source, &[]),
+        (
+            "".into(),
+            "Index 2 out of range for list of length 2.\n    print l[2];\n           ^\n".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn a_list_containing_itself_prints_once() {
+    let source = "var l = [1];\npush(l, l);\nprint l;\nprint [l, l];";
+    assert_eq!(
+        run("run", source, &[]),
+        (
+            "[1, [...]]\n[[1, [...]], [1, [...]]]\n".into(),
+            "".into(),
+            0
+        )
+    );
+}
+
+#[test]
+fn chained_assignment_writes_an_element_and_a_property() {
+    let source = r#"
+class Point {}
+var p = Point();
+var l = [0, 0];
+var m = {};
+print l[0] = p.x = m["k"] = 5;
+print l, p.x, m;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("5\n[5, 0] 5 {k: 5}\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn an_invalid_target_in_a_chain_is_a_parse_error() {
+    let (stdout, stderr, code) = run("run", "var a = [0];\na[0] = 1 + a[0] = 2;", &[]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert!(
+        stderr.starts_with("[line 2] Error at '=': Invalid assignment target."),
+        "{stderr}"
+    );
+}
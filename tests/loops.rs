@@ -0,0 +1,128 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn repeat_runs_a_fixed_count() {
+    let source = "var n = 0;\nrepeat (3) n = n + 1;\nprint n;";
+    assert_eq!(run("run", source, &[]), ("3\n".into(), "".into(), 0));
+}
+
+#[test]
+fn repeat_zero_or_negative_times_skips_the_body() {
+    let source = "repeat (0) print \"zero\";\nrepeat (-2) print \"negative\";\nprint \"done\";";
+    assert_eq!(run("run", source, &[]), ("done\n".into(), "".into(), 0));
+}
+
+#[test]
+fn repeat_truncates_a_fractional_count() {
+    let source = "var n = 0;\nrepeat (2.9) n = n + 1;\nprint n;";
+    assert_eq!(run("run", source, &[]), ("2\n".into(), "".into(), 0));
+}
+
+#[test]
+fn repeat_counts_towards_the_iteration_cap() {
+    let source = "repeat (5) print \"x\";";
+    assert_eq!(
+        run("run", source, &["--max-iterations", "2"]),
+        ("x\nx\n".into(), "Loop exceeded 2 iterations.\n".into(), 70)
+    );
+}
+
+#[test]
+fn break_leaves_the_innermost_loop() {
+    let source = r#"
+var i = 0;
+while (true) {
+  i = i + 1;
+  if (i == 3) break;
+}
+print i;
+for (var j = 0; j < 2; j = j + 1) {
+  repeat (10) {
+    print j;
+    break;
+  }
+}
+do {
+  print "once";
+  break;
+} while (true);
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("3\n0\n1\nonce\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn continue_skips_to_the_next_pass() {
+    let source = r#"
+for (var i = 0; i < 5; i = i + 1) {
+  if (i % 2 == 0) continue;
+  print i;
+}
+var n = 0;
+repeat (4) {
+  n = n + 1;
+  if (n == 2) continue;
+  print n;
+}
+var k = 0;
+while (k < 3) {
+  k = k + 1;
+  if (k == 1) continue;
+  print k;
+}
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("1\n3\n1\n3\n4\n2\n3\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn loop_exits_outside_a_loop_are_parse_errors() {
+    let (stdout, stderr, code) = run("run", "break;", &[]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert!(stderr.contains("[line 1] Error at 'break': Can't use 'break' outside of a loop."));
+
+    let source = "while (true) {\n  fun f() { continue; }\n  break;\n}";
+    let (_, stderr, code) = run("run", source, &[]);
+    assert_eq!(code, 65);
+    assert!(
+        stderr.contains("[line 2] Error at 'continue': Can't use 'continue' outside of a loop.")
+    );
+}
+
+#[test]
+fn iteration_cap_stops_infinite_loops() {
+    for source in ["while (true) {}", "for (;;) {}", "do {} while (true);"] {
+        assert_eq!(
+            run("run", source, &["--max-iterations", "3"]),
+            ("".into(), "Loop exceeded 3 iterations.\n".into(), 70),
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        run(
+            "run",
+            "var i = 0;\nwhile (i < 3) i = i + 1;\nprint i;",
+            &["--max-iterations", "3"]
+        ),
+        ("3\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn do_while_runs_the_body_before_the_first_check() {
+    let source = "var n = 0;\ndo {\n  n = n + 1;\n  print \"body\";\n} while (false);\nprint n;";
+    assert_eq!(run("run", source, &[]), ("body\n1\n".into(), "".into(), 0));
+}
+
+#[test]
+fn counting_do_while_stops_at_its_condition() {
+    let source = "var i = 0;\ndo i = i + 1; while (i < 5);\nprint i;";
+    assert_eq!(run("run", source, &[]), ("5\n".into(), "".into(), 0));
+}
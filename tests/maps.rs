@@ -0,0 +1,56 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn map_literals_keep_insertion_order() {
+    let source = "print {};\nprint {\"b\": 1, \"a\": [2], 3: {\"c\": nil}};";
+    assert_eq!(
+        run("run", source, &[]),
+        ("{}\n{b: 1, a: [2], 3: {c: nil}}\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn index_gets_and_sets_entries() {
+    let source = r#"
+var m = {"a": 1};
+m["a"] = m["a"] + 1;
+m[2] = "two";
+print m["a"], m[2];
+print m;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("2 two\n{a: 2, 2: two}\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn missing_keys_read_as_nil() {
+    let source = "var m = {\"a\": nil};\nprint m[\"b\"], has(m, \"a\"), has(m, \"b\");";
+    assert_eq!(
+        run("run", source, &[]),
+        ("nil true false\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn a_map_containing_itself_prints_once() {
+    let source = "var m = {\"a\": 1};\nm[\"self\"] = m;\nm[\"list\"] = [m];\nprint m;";
+    assert_eq!(
+        run("run", source, &[]),
+        ("{a: 1, self: {...}, list: [{...}]}\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn only_strings_and_numbers_are_keys() {
+    let source = "var m = {};\nm[1] = \"n\";\nm[\"1\"] = \"s\";\nm[0] = \"z\";\nprint m[-0], m[1], m[\"1\"], len(m);\nm[nil] = 1;";
+    let (stdout, stderr, code) = run("run", source, &[]);
+    assert_eq!((stdout.as_str(), code), ("z n s 3\n", 70));
+    assert_eq!(
+        stderr.lines().next(),
+        Some("Map keys must be strings or numbers.")
+    );
+}
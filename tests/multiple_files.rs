@@ -0,0 +1,35 @@
+mod common;
+
+use common::{run, run_files};
+
+#[test]
+fn later_files_see_globals_from_earlier_ones() {
+    let first = "var greeting = \"hello\";\nfun shout(s) { return s + \"!\"; }";
+    let second = "print shout(greeting);";
+    assert_eq!(
+        run_files("run", &[first, second], &[]),
+        ("hello!\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn errors_name_the_file_they_came_from() {
+    let (stdout, stderr, code) = run_files("run", &["print 1;", "print 1 +;", "print 3;"], &[]);
+    assert_eq!((stdout.as_str(), code), ("1\n", 65));
+    let last = stderr.lines().last().unwrap();
+    assert!(
+        last.starts_with("Error in ") && last.ends_with(".lox."),
+        "{stderr}"
+    );
+
+    let (stdout, stderr, code) = run_files("run", &["var a = 1;", "print a - nil;"], &[]);
+    assert_eq!((stdout.as_str(), code), ("", 70));
+    assert!(
+        stderr.lines().last().unwrap().starts_with("Error in "),
+        "{stderr}"
+    );
+
+    //? A lone file needs no name.
+    let (_, stderr, _) = run("run", "print 1 - nil;", &[]);
+    assert!(!stderr.contains("Error in "), "{stderr}");
+}
@@ -0,0 +1,386 @@
+mod common;
+
+use common::run;
+
+fn output(source: &str) -> String {
+    run("run", source, &[]).0
+}
+
+fn error(source: &str) -> (String, i32) {
+    let (_, stderr, code) = run("run", source, &[]);
+    (stderr.lines().next().unwrap_or_default().to_string(), code)
+}
+
+#[test]
+fn substr_counts_chars() {
+    assert_eq!(output(r#"print substr("hello", 1, 3);"#), "ell\n");
+    assert_eq!(output(r#"print substr("héllo wörld", 1, 4);"#), "éllo\n");
+    assert_eq!(output(r#"print substr("日本語", 2, 1);"#), "語\n");
+}
+
+#[test]
+fn substr_boundaries() {
+    assert_eq!(output(r#"print substr("abc", 1, 0) == "";"#), "true\n");
+    assert_eq!(output(r#"print substr("abc", 3, 0) == "";"#), "true\n");
+    assert_eq!(output(r#"print substr("abc", 0, 3);"#), "abc\n");
+    assert_eq!(
+        error(r#"print substr("abc", 2, 2);"#),
+        (
+            "substr() range 2..4 out of range for string of length 3.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error(r#"print substr("abc", 4, 0);"#),
+        (
+            "substr() range 4..4 out of range for string of length 3.".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn substr_huge_length_is_a_runtime_error() {
+    let (message, code) = error(r#"print substr("abc", 1, 100000000000000000000);"#);
+    assert!(message.starts_with("substr() range 1.."), "{message}");
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn substr_rejects_negative_start() {
+    assert_eq!(
+        error(r#"print substr("abc", -1, 1);"#),
+        (
+            "substr() expects a non-negative integer index but got -1.".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn char_at_counts_chars() {
+    assert_eq!(output(r#"print charAt("abc", 2);"#), "c\n");
+    assert_eq!(output(r#"print charAt("naïve", 2);"#), "ï\n");
+    assert_eq!(
+        error(r#"print charAt("naïve", 5);"#),
+        (
+            "charAt() index 5 out of range for string of length 5.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error(r#"print charAt("abc", -1);"#),
+        (
+            "charAt() expects a non-negative integer index but got -1.".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn sort_by_orders_records_by_a_numeric_key() {
+    let source = r#"
+var people = [
+  {"name": "Ann", "age": 41},
+  {"name": "Bob", "age": 23},
+  {"name": "Cy", "age": 35},
+  {"name": "Di", "age": 23}
+];
+fun age(person) { return person["age"]; }
+sort_by(people, age);
+for (var i = 0; i < len(people); i = i + 1) write(people[i]["name"] + " ");
+"#;
+    assert_eq!(output(source), "Bob Di Cy Ann ");
+}
+
+#[test]
+fn sort_by_accepts_natives_as_keys() {
+    assert_eq!(
+        output(r#"var words = ["pear", "fig", "banana"]; sort_by(words, len); print words;"#),
+        "[fig, pear, banana]\n"
+    );
+    assert_eq!(
+        output("var empty = []; sort_by(empty, len); print empty;"),
+        "[]\n"
+    );
+}
+
+#[test]
+fn sort_by_rejects_incomparable_keys() {
+    assert_eq!(
+        error(r#"fun key(x) { return x; } sort_by([1, "a"], key);"#),
+        (
+            "sort_by() keys must be mutually comparable but got number and string.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("sort_by([0 / 0], abs);"),
+        ("sort_by() keys must not be NaN.".into(), 70)
+    );
+    assert_eq!(
+        error("sort_by([1], pow);"),
+        (
+            "sort_by() expects a function of one argument but got function.".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn pure_runs_have_no_natives() {
+    let source = "print len(\"ab\");";
+    assert_eq!(output(source), "2\n");
+    assert_eq!(
+        run("run", source, &["--pure"]),
+        (
+            "".into(),
+            "[line 1] Error at 'len': Undefined variable 'len'.\n    print len(\"ab\");\n          ^\n"
+                .into(),
+            65
+        )
+    );
+    let source = "fun len(s) { return 42; }\nprint len(\"ab\");";
+    assert_eq!(
+        run("run", source, &["--pure"]),
+        ("42\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn len_counts_chars() {
+    assert_eq!(output(r#"print len("abc");"#), "3\n");
+    assert_eq!(output(r#"print len("héllo");"#), "5\n");
+    assert_eq!(output(r#"print len("");"#), "0\n");
+}
+
+#[test]
+fn len_rejects_bad_arguments() {
+    assert_eq!(
+        error("print len(1);"),
+        (
+            "len() expects a string, list or map but got number.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("print len();"),
+        ("Expected 1 arguments but got 0.".into(), 70)
+    );
+    assert_eq!(
+        error(r#"print len("a", "b");"#),
+        ("Expected 1 arguments but got 2.".into(), 70)
+    );
+}
+
+#[test]
+fn str_renders_any_value() {
+    assert_eq!(output("print str(42) + \"!\";"), "42!\n");
+    assert_eq!(
+        output("print str(2.5) + str(nil) + str(true);"),
+        "2.5niltrue\n"
+    );
+}
+
+#[test]
+fn num_parses_numeric_strings_and_returns_nil_otherwise() {
+    assert_eq!(output(r#"print num("3.5") + 1;"#), "4.5\n");
+    assert_eq!(output(r#"print num("abc");"#), "nil\n");
+    assert_eq!(
+        error("print num(1);"),
+        ("num() expects a string but got number.".into(), 70)
+    );
+}
+
+#[test]
+fn write_leaves_the_line_open_for_print() {
+    assert_eq!(
+        run(
+            "run",
+            "write(\"a\");\nwrite(\"b\");\nprint \"\";\nwrite(12);\nprint \"c\";",
+            &[]
+        ),
+        ("ab\n12c\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn digits_counts_decimal_digits_ignoring_the_sign() {
+    assert_eq!(
+        output("print digits(12345), digits(-42), digits(0);"),
+        "5 2 1\n"
+    );
+    assert_eq!(
+        error("print digits(1.5);"),
+        ("digits() expects an integer but got 1.5.".into(), 70)
+    );
+    assert_eq!(
+        error(r#"print digits("1");"#),
+        ("digits() expects a number but got string.".into(), 70)
+    );
+}
+
+#[test]
+fn chr_and_ord_convert_code_points() {
+    assert_eq!(output("print chr(65), ord(\"A\");"), "A 65\n");
+    assert_eq!(output("print ord(\"語\"), chr(35486);"), "35486 語\n");
+}
+
+#[test]
+fn chr_and_ord_reject_bad_input() {
+    for code_point in ["55296", "1114112", "-1", "65.5"] {
+        assert_eq!(
+            error(&format!("print chr({code_point});")),
+            (format!("chr() got invalid code point {code_point}."), 70)
+        );
+    }
+    assert_eq!(
+        error(r#"print ord("ab");"#),
+        (
+            "ord() expects a single character but got \"ab\".".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn passing_assert_continues() {
+    assert_eq!(
+        run("run", "assert(true, \"no\");\nprint assert(1);", &[]),
+        ("nil\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn failing_assert_aborts_with_its_message() {
+    assert_eq!(
+        run(
+            "run",
+            "print \"before\";\nassert(1 > 2, \"math broke\");\nprint \"after\";",
+            &[]
+        ),
+        (
+            "before\n".into(),
+            "Assertion failed: math broke\n".into(),
+            70
+        )
+    );
+    assert_eq!(
+        run("run", "assert(nil);", &[]),
+        ("".into(), "Assertion failed.\n".into(), 70)
+    );
+}
+
+#[test]
+fn math_natives_compute_on_numbers() {
+    assert_eq!(
+        output("print abs(-3), floor(2.7), ceil(2.1), sqrt(16), pow(2, 10);"),
+        "3 2 3 4 1024\n"
+    );
+    assert_eq!(output("print sqrt(-1);"), "NaN\n");
+}
+
+#[test]
+fn math_natives_reject_non_numbers() {
+    for (call, message) in [
+        ("abs(\"a\")", "abs() expects a number but got string."),
+        ("floor(nil)", "floor() expects a number but got nil."),
+        ("ceil(true)", "ceil() expects a number but got boolean."),
+        ("sqrt(\"4\")", "sqrt() expects a number but got string."),
+        ("pow(2, \"a\")", "pow() expects a number but got string."),
+    ] {
+        assert_eq!(error(&format!("print {call};")), (message.into(), 70));
+    }
+}
+
+#[test]
+fn string_natives_convert_case_and_trim() {
+    assert_eq!(
+        output(r#"print upper("héllo"), lower("ABC"), trim("  x y  ") + "|";"#),
+        "HÉLLO abc x y|\n"
+    );
+}
+
+#[test]
+fn split_on_a_separator_or_into_chars() {
+    assert_eq!(
+        output(r#"print split("a,b,,c", ","), split("abc", "");"#),
+        "[a, b, , c] [a, b, c]\n"
+    );
+}
+
+#[test]
+fn string_natives_reject_non_strings() {
+    assert_eq!(
+        error("print upper(1);"),
+        ("upper() expects a string but got number.".into(), 70)
+    );
+    assert_eq!(
+        error("print trim(nil);"),
+        ("trim() expects a string but got nil.".into(), 70)
+    );
+    assert_eq!(
+        error(r#"print split("a", 1);"#),
+        ("split() expects a string but got number.".into(), 70)
+    );
+}
+
+#[test]
+fn typeof_names_every_value_kind() {
+    let source = r#"
+class C {}
+fun f() {}
+print typeof(1), typeof(true), typeof("s"), typeof(nil);
+print typeof(f), typeof(len), typeof(C), typeof(C());
+print typeof([]), typeof({});
+"#;
+    assert_eq!(
+        output(source),
+        "number boolean string nil\nfunction function class instance\nlist map\n"
+    );
+}
+
+#[test]
+fn format_fills_placeholders_in_order() {
+    assert_eq!(
+        output(r#"print format("{} + {} = {}", 1, 2, 3);"#),
+        "1 + 2 = 3\n"
+    );
+    assert_eq!(output(r#"print format("{{}} and {}", "x");"#), "{} and x\n");
+}
+
+#[test]
+fn format_rejects_mismatched_counts() {
+    assert_eq!(
+        error(r#"print format("{}", 1, 2);"#),
+        ("format() has 1 placeholders but got 2 values.".into(), 70)
+    );
+    assert_eq!(
+        error(r#"print format("{} {}", 1);"#),
+        ("format() has 2 placeholders but got 1 values.".into(), 70)
+    );
+}
+
+#[test]
+fn min_and_max_pick_among_their_arguments() {
+    assert_eq!(
+        output("print max(3, 7, 2), min(3, 7, 2), max(-1, -5);"),
+        "7 2 -1\n"
+    );
+}
+
+#[test]
+fn min_and_max_need_two_numbers() {
+    assert_eq!(
+        error("print max(1);"),
+        ("Expected at least 2 arguments but got 1.".into(), 70)
+    );
+    assert_eq!(
+        error("print min();"),
+        ("Expected at least 2 arguments but got 0.".into(), 70)
+    );
+    assert_eq!(
+        error(r#"print max(1, "a");"#),
+        ("max() expects a number but got string.".into(), 70)
+    );
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::run;
+
+fn output(source: &str) -> String {
+    run("run", source, &[]).0
+}
+
+#[test]
+fn whole_numbers_print_without_a_fraction() {
+    assert_eq!(output("print 10 / 2;"), "5\n");
+    assert_eq!(output("print 5.0;"), "5\n");
+    assert_eq!(output("print -3 * 2;"), "-6\n");
+}
+
+#[test]
+fn fractions_print_their_shortest_round_trip_form() {
+    assert_eq!(output("print 1.5;"), "1.5\n");
+    assert_eq!(output("print 3.14;"), "3.14\n");
+    assert_eq!(output("print 2.50;"), "2.5\n");
+    assert_eq!(output("print 0.1 + 0.2;"), "0.30000000000000004\n");
+    assert_eq!(output("print 1 / 3;"), "0.3333333333333333\n");
+}
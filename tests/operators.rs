@@ -0,0 +1,91 @@
+mod common;
+
+use common::run;
+
+fn output(source: &str) -> String {
+    run("run", source, &[]).0
+}
+
+#[test]
+fn bitwise_operators_work_on_integers() {
+    assert_eq!(
+        output("print 6 & 3, 4 | 1, 5 ^ 1, 1 << 3, 16 >> 2;"),
+        "2 5 4 8 4\n"
+    );
+}
+
+#[test]
+fn bitwise_operators_reject_fractions() {
+    assert_eq!(
+        run("run", "print 1.5 & 2;", &[]),
+        (
+            "".into(),
+            "[line 1] Operands must be integers.\n    print 1.5 & 2;\n              ^\n".into(),
+            70
+        )
+    );
+}
+
+#[test]
+fn compound_assignment_updates_the_variable() {
+    assert_eq!(
+        output("var x = 10;\nx += 2;\nprint x;\nx -= 3;\nprint x;\nx *= 4;\nprint x;\nx /= 6;\nprint x;"),
+        "12\n9\n36\n6\n"
+    );
+    assert_eq!(output("var s = \"a\";\ns += \"b\";\nprint s;"), "ab\n");
+}
+
+#[test]
+fn compound_assignment_to_an_undefined_variable_errors_like_assign() {
+    for source in ["y = 1;", "y += 1;"] {
+        assert_eq!(
+            run("run", source, &[]),
+            ("".into(), "Undefined assign variable 'y'.\n".into(), 70),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn exponent_is_right_associative_and_binds_above_factor() {
+    assert_eq!(
+        output("print 2 ** 3 ** 2, 9 ** 0.5, 2 * 3 ** 2;"),
+        "512 3 18\n"
+    );
+    assert_eq!(
+        run("parse", "2 ** 3 ** 2", &[]).0,
+        "(** 2.0 (** 3.0 2.0))\n"
+    );
+}
+
+#[test]
+fn exponent_rejects_non_numbers() {
+    let (_, stderr, code) = run("run", "print \"a\" ** 2;", &[]);
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 1] Operands must be numbers, got string and number.")
+    );
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn comma_yields_the_last_value_after_running_every_operand() {
+    assert_eq!(run("evaluate", "(1, 2, 3)", &[]).0, "3\n");
+    assert_eq!(
+        output("var a = 0;\nvar b = (a = 5, a + 1);\nprint a, b;"),
+        "5 6\n"
+    );
+    assert_eq!(
+        output("var j = 10;\nfor (var i = 0; i < 2; i = i + 1, j = j - 1) print i, j;"),
+        "0 10\n1 9\n"
+    );
+}
+
+#[test]
+fn comma_leaves_calls_and_list_literals_alone() {
+    assert_eq!(
+        output("fun f(x, y) { return x + y; }\nprint f(1, 2), [1, 2];"),
+        "3 [1, 2]\n"
+    );
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::run;
+
+fn optimized(source: &str) -> String {
+    run("parse", source, &["--optimize"]).0
+}
+
+#[test]
+fn folds_constant_arithmetic_bottom_up() {
+    assert_eq!(optimized("print 1 + 2 * 3;"), "print 7.0\n");
+    assert_eq!(optimized("print (1 + 2) * 3;"), "print 9.0\n");
+    assert_eq!(
+        run("parse", "print 1 + 2 * 3;", &[]).0,
+        "print (+ 1.0 (* 2.0 3.0))\n"
+    );
+}
+
+#[test]
+fn folds_strings_and_comparisons() {
+    assert_eq!(
+        optimized(r#"print "a" + "b", 1 < 2, "b" <= "a";"#),
+        "print ab, true, false\n"
+    );
+}
+
+#[test]
+fn leaves_variables_and_failing_operations_unfolded() {
+    assert_eq!(
+        optimized("var x = 1;\nprint x + 1;"),
+        "var x = 1.0\nprint (+ x 1.0)\n"
+    );
+    assert_eq!(optimized("print 1 / 0;"), "print (/ 1.0 0.0)\n");
+    assert_eq!(optimized(r#"print "a" + 1;"#), "print (+ a 1.0)\n");
+}
+
+#[test]
+fn folds_modulo_and_power() {
+    assert_eq!(optimized("print 7 % 3;"), "print 1.0\n");
+    assert_eq!(optimized("print 2 ** 10;"), "print 1024.0\n");
+    assert_eq!(optimized("print -7 % 3;"), "print -1.0\n");
+}
+
+#[test]
+fn leaves_modulo_by_zero_for_runtime() {
+    assert_eq!(optimized("print 7 % 0;"), "print (% 7.0 0.0)\n");
+    let (stdout, stderr, code) = run("run", "print 1;\nprint 7 % 0;", &["--optimize"]);
+    assert_eq!(stdout, "1\n");
+    assert_eq!(stderr.lines().next(), Some("[line 2] Modulo by zero."));
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn modulo_binds_like_multiplication() {
+    assert_eq!(
+        run("run", "print 1 + 10 % 4 * 2, 7.5 % 2;", &[]).0,
+        "5 1.5\n"
+    );
+}
+
+#[test]
+fn folds_negated_literals_but_not_variables() {
+    assert_eq!(optimized("print -5;"), "print -5.0\n");
+    assert_eq!(optimized("print - -5;"), "print 5.0\n");
+    assert_eq!(optimized("print -x;"), "print (- x)\n");
+    assert_eq!(optimized("print -5 < 0;"), "print true\n");
+    assert_eq!(
+        run("run", "var x = 3;\nprint -5, - -5, -x, -5 < 0;", &[]).0,
+        "-5 5 -3 true\n"
+    );
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn grouping_overrides_precedence() {
+    assert_eq!(
+        run("parse", "(1 + 2) * 3", &[]),
+        ("(* (group (+ 1.0 2.0)) 3.0)\n".into(), "".into(), 0)
+    );
+    assert_eq!(
+        run("evaluate", "(1 + 2) * 3", &[]),
+        ("9\n".into(), "".into(), 0)
+    );
+    assert_eq!(
+        run("evaluate", "((\"a\"))", &[]),
+        ("a\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn a_group_holds_an_expression_not_a_statement() {
+    let (stdout, stderr, code) = run("parse", "(var a = 1)", &[]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert!(
+        stderr.starts_with("[line 1] Error at 'var': Expect expression."),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn statements_starting_with_an_identifier_parse_from_the_identifier() {
+    let source = "var x = 1;\nx;\nx + 1;\nx = x + 1;\nprint x;";
+    assert_eq!(
+        run("parse", source, &[]).0,
+        "var x = 1.0\nx\n(+ x 1.0)\nx = (+ x 1.0)\nprint x\n"
+    );
+    assert_eq!(run("run", source, &[]), ("2\n".into(), "".into(), 0));
+}
+
+#[test]
+fn var_without_an_initializer_is_nil() {
+    assert_eq!(
+        run("run", "var x;\nprint x;", &[]),
+        ("nil\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn var_without_a_name_is_still_a_parse_error() {
+    for source in ["var;", "var = 1;"] {
+        let (stdout, stderr, code) = run("run", source, &[]);
+        assert_eq!((stdout.as_str(), code), ("", 65), "{}", source);
+        assert!(
+            stderr.starts_with("[line 1] Error at 'var': Expect expression."),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn a_single_trailing_comma_is_allowed() {
+    let source = "fun f(a, b,) { return a + b; }\nprint f(1, 2,), [1, 2,], {\"a\": 1,};";
+    assert_eq!(
+        run("run", source, &[]),
+        ("3 [1, 2] {a: 1}\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn leading_and_double_commas_are_parse_errors() {
+    for source in ["print [1,, 2];", "print [,1];", "fun f(a) {}\nf(1,,);"] {
+        let (_, stderr, code) = run("run", source, &[]);
+        assert_eq!(code, 65, "{}", source);
+        assert!(
+            stderr.contains("Error at ',': Expect expression."),
+            "{stderr}"
+        );
+    }
+}
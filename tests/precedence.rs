@@ -0,0 +1,29 @@
+mod common;
+
+use common::run;
+
+fn parsed(source: &str) -> String {
+    run("parse", source, &[]).0
+}
+
+#[test]
+fn binary_operators_associate_left() {
+    assert_eq!(parsed("1 - 2 - 3"), "(- (- 1.0 2.0) 3.0)\n");
+    assert_eq!(parsed("8 / 4 / 2"), "(/ (/ 8.0 4.0) 2.0)\n");
+    assert_eq!(parsed("1 < 2 == true"), "(== (< 1.0 2.0) true)\n");
+}
+
+#[test]
+fn mixed_operators_bind_by_precedence() {
+    assert_eq!(parsed("2 * 3 + 4 * 5"), "(+ (* 2.0 3.0) (* 4.0 5.0))\n");
+    assert_eq!(parsed("7 % 3 * 2"), "(* (% 7.0 3.0) 2.0)\n");
+    assert_eq!(
+        parsed("-2 * -3 < 4 / 2"),
+        "(< (* (- 2.0) (- 3.0)) (/ 4.0 2.0))\n"
+    );
+    assert_eq!(
+        parsed("1 + 2 == 3 and !false or nil"),
+        "(or (and (== (+ 1.0 2.0) 3.0) (! false)) nil)\n"
+    );
+    assert_eq!(parsed("1 | 2 & 3 ^ 4"), "(| 1.0 (^ (& 2.0 3.0) 4.0))\n");
+}
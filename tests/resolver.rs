@@ -0,0 +1,118 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn undefined_variable_is_a_resolve_error() {
+    let (stdout, stderr, code) = run("run", "print 1;\nprint undefinedVar;\n", &[]);
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.starts_with("[line 2] Error at 'undefinedVar': Undefined variable 'undefinedVar'."),
+        "{stderr}"
+    );
+    assert_eq!(code, 65);
+}
+
+#[test]
+fn forward_reference_to_global_function() {
+    let source = r#"
+fun first() { return second() + len("ab"); }
+fun second() { return 1; }
+print first();
+"#;
+    assert_eq!(run("run", source, &[]), ("3\n".into(), "".into(), 0));
+}
+
+#[test]
+fn forward_reference_to_global_declared_under_control_flow() {
+    let source = r#"
+fun show() { print later; print counted; }
+if (true) var later = "if";
+var n = 0;
+while (n < 2) var counted = n = n + 1;
+show();
+"#;
+    assert_eq!(run("run", source, &[]), ("if\n2\n".into(), "".into(), 0));
+}
+
+#[test]
+fn block_declarations_are_not_globals() {
+    let source = r#"
+fun show() { print hidden; }
+{ if (true) var hidden = 1; }
+show();
+"#;
+    let (_, stderr, code) = run("run", source, &[]);
+    assert!(
+        stderr.starts_with("[line 2] Error at 'hidden': Undefined variable 'hidden'."),
+        "{stderr}"
+    );
+    assert_eq!(code, 65);
+}
+
+#[test]
+fn nested_for_declaration_in_block_resolves() {
+    let source = "{ if (true) for (var i = 0; i < 2; i = i + 1) print i; }";
+    assert_eq!(run("run", source, &[]), ("0\n1\n".into(), "".into(), 0));
+}
+
+#[test]
+fn closures_capture_the_binding_in_scope_at_definition() {
+    let source = r#"
+var a = "global";
+{
+  fun show() { print a; }
+  show();
+  var a = "block";
+  show();
+  print a;
+}
+fun counter() {
+  var count = 0;
+  fun increment() {
+    count = count + 1;
+    return count;
+  }
+  return increment;
+}
+var next = counter();
+next();
+print next();
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("global\nglobal\nblock\n2\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn reading_a_local_in_its_own_initializer_is_a_resolve_error() {
+    let (stdout, stderr, code) = run("run", "var a = 1;\n{\n  var a = a;\n}", &[]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert!(
+        stderr.starts_with(
+            "[line 3] Error at 'a': Can't read local variable in its own initializer."
+        ),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_names_reports_an_undefined_local_without_running() {
+    let source = "print \"ran\";\n{\n  var a = 1;\n  print b;\n}";
+    assert_eq!(
+        run("check-names", source, &[]),
+        (
+            "".into(),
+            "[line 4] Error at 'b': Undefined variable 'b'.\n      print b;\n            ^\n"
+                .into(),
+            65
+        )
+    );
+}
+
+#[test]
+fn check_names_accepts_a_forward_referenced_global() {
+    let source = "fun f() { return later; }\nvar later = 1;\nprint f();";
+    assert_eq!(run("check-names", source, &[]), ("".into(), "".into(), 0));
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::run;
+
+//? The first stderr line and the exit code.
+fn error(source: &str) -> (String, i32) {
+    let (_, stderr, code) = run("run", source, &[]);
+    (stderr.lines().next().unwrap_or_default().to_string(), code)
+}
+
+#[test]
+fn operand_errors_name_the_operator_line() {
+    assert_eq!(
+        error("var a = 1;\nvar b = \"x\";\n\nprint a;\nprint a - b;"),
+        (
+            "[line 5] Operands must be numbers, got number and string.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("var a = 1;\nprint a\n  + \"x\";"),
+        (
+            "[line 3] Operands must be two numbers or two strings, got number and string.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("print 1;\nprint 2 < nil;"),
+        (
+            "[line 2] Operands must be numbers or strings, got number and nil.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("print 1;\nprint 1.5 & 2;"),
+        ("[line 2] Operands must be integers.".into(), 70)
+    );
+}
+
+#[test]
+fn statements_before_the_error_still_run() {
+    let (stdout, stderr, code) = run("run", "print 1;\nprint 2;\nprint true * 2;", &[]);
+    assert_eq!(stdout, "1\n2\n");
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 3] Operands must be numbers, got boolean and number.")
+    );
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn runtime_errors_show_a_snippet_with_a_caret_at_the_operator() {
+    assert_eq!(
+        run("run", "var a = 1;\nprint a - \"b\";", &[]),
+        (
+            "".into(),
+            "[line 2] Operands must be numbers, got number and string.\n    print a - \"b\";\n            ^\n"
+                .into(),
+            70
+        )
+    );
+    assert_eq!(
+        run("run", "print -\"x\";", &[]).1,
+        "Operand must be a number.\n    print -\"x\";\n          ^\n"
+    );
+}
+
+#[test]
+fn booleans_are_not_ordered() {
+    assert_eq!(
+        error("print true < false;"),
+        (
+            "[line 1] Operands must be numbers or strings, got boolean and boolean.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        run("run", "print \"a\" < \"b\", 1 < 2;", &[]).0,
+        "true true\n"
+    );
+}
+
+#[test]
+fn binary_errors_name_both_operands_and_unary_errors_one() {
+    assert_eq!(
+        error("print \"a\" - 1;"),
+        (
+            "[line 1] Operands must be numbers, got string and number.".into(),
+            70
+        )
+    );
+    assert_eq!(
+        error("print -\"x\";"),
+        ("Operand must be a number.".into(), 70)
+    );
+}
+
+#[test]
+fn multi_line_expressions_report_the_operator_line() {
+    assert_eq!(
+        run("run", "print 1 +\n  \"x\";", &[]).1,
+        "[line 1] Operands must be two numbers or two strings, got number and string.\n    print 1 +\n            ^\n"
+    );
+    assert_eq!(
+        run("run", "print 1\n  - nil;", &[]).1,
+        "[line 2] Operands must be numbers, got number and nil.\n      - nil;\n      ^\n"
+    );
+}
+
+#[test]
+fn multi_line_parse_errors_report_the_offending_token_line() {
+    assert_eq!(
+        run("run", "print 1 +\n  ;", &[]),
+        (
+            "".into(),
+            "[line 2] Error at ';': Expect expression.\n      ;\n      ^\n".into(),
+            65
+        )
+    );
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn declaration_under_if_stays_in_its_block() {
+    let source = r#"
+var y = "outer";
+{
+  if (true) var y = "inner";
+  print y;
+}
+print y;
+"#;
+    assert_eq!(
+        run("run", source, &[]),
+        ("inner\nouter\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn for_clause_declared_under_if_resolves() {
+    let source = "{ if (true) for (var i = 0; i < 2; i = i + 1) print i; }";
+    assert_eq!(run("run", source, &[]), ("0\n1\n".into(), "".into(), 0));
+}
+
+#[test]
+fn declaration_in_loop_body_stays_in_its_block() {
+    let source = r#"
+var x = "outer";
+{
+  var n = 0;
+  while (n < 1) var x = n = n + 1;
+}
+print x;
+"#;
+    assert_eq!(run("run", source, &[]), ("outer\n".into(), "".into(), 0));
+}
+
+#[test]
+fn block_without_declarations_assigns_outer_variable() {
+    let source = r#"
+var total = 0;
+for (var i = 0; i < 3; i = i + 1) {
+  total = total + i;
+}
+print total;
+"#;
+    assert_eq!(run("run", source, &[]), ("3\n".into(), "".into(), 0));
+}
+
+#[test]
+fn assigning_to_a_constant_is_a_runtime_error() {
+    let source = "const x = 1;\nprint x;\nx = 2;\nprint x;";
+    assert_eq!(
+        run("run", source, &[]),
+        ("1\n".into(), "Cannot assign to constant 'x'.\n".into(), 70)
+    );
+}
+
+#[test]
+fn constants_can_be_shadowed_in_an_inner_block() {
+    let source = "const x = 1;\n{\n  const x = 2;\n  print x;\n  var y = x;\n  y = 3;\n  print y;\n}\nprint x;";
+    assert_eq!(run("run", source, &[]), ("2\n3\n1\n".into(), "".into(), 0));
+}
+
+#[test]
+fn env_dump_lists_globals_in_definition_order() {
+    let source = "var zeta = 1;\nvar alpha = \"a\";\nvar mid = nil;\nzeta = 2;";
+    for _ in 0..5 {
+        assert_eq!(
+            run("env-dump", source, &[]),
+            ("zeta = 2\nalpha = a\nmid = nil\n".into(), "".into(), 0)
+        );
+    }
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn show_types_tags_every_value_kind() {
+    let source = r#"
+print 3;
+print "hi";
+print true;
+print nil;
+print [1];
+print {"a": 1};
+fun f() {}
+print f;
+print len;
+class C {}
+print C;
+print C();
+print 1, "a";
+"#;
+    let expected = "\
+3 : number
+hi : string
+true : boolean
+nil : nil
+[1] : list
+{a: 1} : map
+<fn f> : function
+<native fn len> : function
+<class C> : class
+C instance : instance
+1 : number a : string
+";
+    assert_eq!(
+        run("run", source, &["--show-types"]),
+        (expected.into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn show_types_tags_the_evaluated_value() {
+    assert_eq!(
+        run("evaluate", "1 + 2", &["--show-types"]),
+        ("3 : number\n".into(), "".into(), 0)
+    );
+    assert_eq!(run("evaluate", "1 + 2", &[]), ("3\n".into(), "".into(), 0));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn multi_line_string_prints_its_newline() {
+    assert_eq!(
+        run("run", "print \"one\ntwo\";", &[]),
+        ("one\ntwo\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn errors_after_a_multi_line_string_report_the_right_line() {
+    let (_, stderr, code) = run("run", "var s = \"one\ntwo\";\nprint ;", &[]);
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 3] Error at ';': Expect expression.")
+    );
+    assert_eq!(code, 65);
+    let (_, stderr, code) = run("run", "var s = \"one\ntwo\";\nprint -s;", &[]);
+    assert_eq!(stderr.lines().next(), Some("Operand must be a number."));
+    assert_eq!(stderr.lines().nth(1), Some("    print -s;"));
+    assert_eq!(code, 70);
+}
+
+#[test]
+fn unicode_escapes_decode_to_characters() {
+    assert_eq!(
+        run("run", "print \"a\\u{1F600}b\", \"\\u{41}\";", &[]),
+        ("a😀b A\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn invalid_unicode_escapes_are_scan_errors() {
+    assert_eq!(
+        run("run", "print \"\\u{110000}\";", &[]),
+        (
+            "".into(),
+            "[line 1] Error: Invalid code point in unicode escape.\n    print \"\\u{110000}\";\n           ^\n"
+                .into(),
+            65
+        )
+    );
+    for source in ["print \"\\u{41\";", "print \"\\u{}\";"] {
+        let (_, stderr, code) = run("run", source, &[]);
+        assert_eq!(code, 65, "{}", source);
+        assert!(
+            stderr.starts_with("[line 1] Error: Invalid unicode escape."),
+            "{stderr}"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn tokens_only_counts_every_token_by_category() {
+    let (stdout, stderr, code) = run("--tokens-only", "var a = (1 + 2) * x;\nprint a;\n", &[]);
+    assert_eq!(
+        stdout,
+        "tokens: 14\nkeywords: 2\nidentifiers: 3\nliterals: 2\noperators: 3\npunctuation: 4\nerrors: 0\nlines: 2\n"
+    );
+    assert_eq!((stderr.as_str(), code), ("", 0));
+}
+
+#[test]
+fn tokens_only_includes_unscannable_characters_in_the_total() {
+    let (stdout, stderr, code) = run("--tokens-only", "print 1; @ #", &[]);
+    assert_eq!(
+        stdout,
+        "tokens: 5\nkeywords: 1\nidentifiers: 0\nliterals: 1\noperators: 0\npunctuation: 1\nerrors: 2\nlines: 1\n"
+    );
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 1] Error: Unexpected character: @")
+    );
+    assert_eq!(code, 65);
+}
+
+#[test]
+fn approx_uax31_accepts_accented_identifiers() {
+    //? `café` precomposed, then spelled with a combining acute accent.
+    let source = "var caf\u{e9} = 1;\nvar cafe\u{301} = 2;\nprint caf\u{e9}, cafe\u{301};";
+    assert_eq!(
+        run("run", source, &["--approx-uax31"]),
+        ("1 2\n".into(), "".into(), 0)
+    );
+    let (_, stderr, code) = run("run", source, &[]);
+    assert_eq!(code, 65);
+    assert!(stderr.starts_with("[line 2] Error: Unexpected character: \u{301}"));
+}
+
+#[test]
+fn approx_uax31_rejects_a_combining_mark_start() {
+    let (stdout, stderr, code) = run("run", "var \u{301}x = 1;", &["--approx-uax31"]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert_eq!(
+        stderr.lines().next(),
+        Some("[line 1] Error: Identifier cannot start with combining mark U+0301.")
+    );
+}
+
+#[test]
+fn hex_literals_scan_as_numbers() {
+    assert_eq!(
+        run("run", "print 0xFF, 0x10, 0xab, 0X1f;", &[]),
+        ("255 16 171 31\n".into(), "".into(), 0)
+    );
+}
+
+#[test]
+fn bare_hex_prefix_is_a_scan_error() {
+    assert_eq!(
+        run("run", "print 0x;", &[]),
+        (
+            "".into(),
+            "[line 1] Error: Expect hex digits after '0x'.\n    print 0x;\n            ^\n".into(),
+            65
+        )
+    );
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn code_after_return_warns_but_still_runs() {
+    let source = "fun f() {\n  return 1;\n  print 2;\n}\nprint f();";
+    assert_eq!(
+        run("run", source, &[]),
+        (
+            "1\n".into(),
+            "[line 3] Warning at 'print': Unreachable code.\n      print 2;\n      ^\n".into(),
+            0
+        )
+    );
+}
+
+#[test]
+fn strict_makes_unreachable_code_fatal() {
+    let source = "fun f() {\n  return 1;\n  print 2;\n}\nprint f();";
+    let (stdout, stderr, code) = run("run", source, &["--strict"]);
+    assert_eq!((stdout.as_str(), code), ("", 65));
+    assert!(stderr.starts_with("[line 3] Warning at 'print': Unreachable code."));
+}
+
+#[test]
+fn warn_unused_flags_only_unread_locals() {
+    let source =
+        "var g = 1;\nfun f(p) {\n  var unused = 1;\n  var used = 2;\n  print used;\n}\nf(0);";
+    assert_eq!(
+        run("run", source, &["--warn-unused"]),
+        (
+            "2\n".into(),
+            "[line 3] Warning at 'unused': Unused variable 'unused'.\n      var unused = 1;\n          ^\n"
+                .into(),
+            0
+        )
+    );
+    assert_eq!(run("run", source, &[]), ("2\n".into(), "".into(), 0));
+}